@@ -0,0 +1,308 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::{from_fn, Condition, NormalizePath, TrailingSlash};
+use actix_web::{web, App, Error};
+use aws_sdk_s3::Client as S3Client;
+use sqlx::PgPool;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::handlers;
+use crate::middleware::body_limit::{self, BodyLimits};
+use crate::middleware::cors::{self, AllowedOrigins};
+use crate::middleware::errors;
+use crate::middleware::https;
+use crate::middleware::maintenance::{self, MaintenanceMode};
+use crate::middleware::rate_limit::{self, RateLimiter};
+use crate::middleware::readiness::{self, Readiness};
+use crate::utils::scanner::FileScanner;
+use crate::utils::upload_limiter::UploadLimiter;
+
+/// Everything the app's workers share. Built once by `main`, and by the test harness, which
+/// swaps in its own pool and S3 client and adjusts fields before calling `build_app`.
+#[derive(Clone)]
+pub struct AppState {
+    pub config: web::Data<Config>,
+    pub pool: PgPool,
+    pub s3_client: S3Client,
+    pub file_scanner: Arc<dyn FileScanner>,
+    /// Shared across workers so the per-user upload cap holds process-wide
+    pub upload_limiter: web::Data<UploadLimiter>,
+    /// Can be switched on at startup and toggled at runtime
+    pub maintenance_mode: web::Data<MaintenanceMode>,
+    pub readiness: web::Data<Readiness>,
+    /// Per-route limits shared by all workers
+    pub rate_limiter: web::Data<RateLimiter>,
+    pub body_limits: web::Data<BodyLimits>,
+    /// Browser origins allowed to call the API; CORS stays off when none are configured
+    pub cors_origins: AllowedOrigins,
+}
+
+impl AppState {
+    pub fn new(config: Config, pool: PgPool, s3_client: S3Client, file_scanner: Arc<dyn FileScanner>) -> Self {
+        // Every body is read within the timeout and capped at the app-wide size, on top of
+        // the per-extractor JSON and multipart limits
+        let body_limits = BodyLimits::new(config.max_request_body_size, config.request_timeout)
+            .with_route_max_size("/v1/admin/import", config.max_import_body_size);
+
+        AppState {
+            upload_limiter: web::Data::new(UploadLimiter::new(config.max_concurrent_uploads_per_user)),
+            maintenance_mode: web::Data::new(MaintenanceMode::new(config.maintenance_mode)),
+            readiness: web::Data::new(Readiness::new(true)),
+            rate_limiter: web::Data::new(RateLimiter::new(config.rate_limits.clone(), config.rate_limit_window)),
+            body_limits: web::Data::new(body_limits),
+            cors_origins: AllowedOrigins::from_env(),
+            config: web::Data::new(config),
+            pool,
+            s3_client,
+            file_scanner,
+        }
+    }
+}
+
+/// The app with its shared state, middleware and routes, as served to each worker.
+pub fn build_app(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let config = &state.config;
+    let max_import_body_size = config.max_import_body_size;
+
+    App::new()
+        .app_data(config.clone())
+        .app_data(web::Data::new(state.pool.clone()))
+        .app_data(web::Data::new(state.s3_client.clone()))
+        .app_data(web::Data::from(state.file_scanner.clone()))
+        .app_data(state.upload_limiter.clone())
+        .app_data(state.maintenance_mode.clone())
+        .app_data(state.readiness.clone())
+        .app_data(state.rate_limiter.clone())
+        .app_data(state.body_limits.clone())
+        .app_data(web::PayloadConfig::new(config.max_request_body_size))
+        // `Content-Encoding: gzip` JSON bodies are decompressed by the extractor; this
+        // limit applies to the decompressed size so compressed bombs are rejected
+        .app_data(web::JsonConfig::default().limit(config.max_json_body_size))
+        .wrap(from_fn(errors::render_errors))
+        .wrap(from_fn(body_limit::limit_body))
+        .wrap(Condition::new(state.rate_limiter.is_enabled(), from_fn(rate_limit::rate_limit)))
+        .wrap(from_fn(maintenance::reject_writes_during_maintenance))
+        .wrap(from_fn(readiness::reject_until_ready))
+        .wrap(Condition::new(config.require_https, from_fn(https::require_https)))
+        // Outside the other middleware so their rejections also carry CORS headers
+        .wrap(Condition::new(
+            state.cors_origins.is_enabled(),
+            cors::build_cors(state.cors_origins.clone(), config.cors_max_age),
+        ))
+        // Trim trailing slashes so `/v1/user/` resolves the same as `/v1/user`
+        .wrap(NormalizePath::new(TrailingSlash::Trim))
+        .configure(|cfg| routes(cfg, max_import_body_size))
+}
+
+/// Every API route.
+fn routes(cfg: &mut web::ServiceConfig, max_import_body_size: usize) {
+    cfg
+        .service(
+            web::resource("/")
+                .route(web::get().to(handlers::root::index)),
+        )
+        .service(
+            web::resource("/health")
+                .route(web::get().to(handlers::root::index)),
+        )
+        .service(
+            web::resource("/v1/auth")
+                .route(web::post().to(handlers::auth::auth_handler)),
+        )
+        .service(
+            web::resource("/v1/auth/introspect")
+                .route(web::post().to(handlers::auth::introspect_handler)),
+        )
+        .service(
+            web::resource("/v1/user")
+                .route(web::get().to(handlers::user::get_user_profile))
+                .route(web::patch().to(handlers::user::update_user_profile)),
+        )
+        .service(
+            web::resource("/v1/user/export")
+                .route(web::get().to(handlers::user::export_user_data)),
+        )
+        .service(
+            web::resource("/v1/file")
+                .route(web::post().to(handlers::file::upload_file))
+                .route(web::get().to(handlers::file::list_files)),
+        )
+        .service(
+            web::resource("/v1/file/presign")
+                .route(web::post().to(handlers::file::presign_uploads)),
+        )
+        .service(
+            web::resource("/v1/file/confirm")
+                .route(web::post().to(handlers::file::confirm_upload)),
+        )
+        .service(
+            web::resource("/v1/file/{file_id}/metadata")
+                .route(web::get().to(handlers::file::get_file_metadata)),
+        )
+        .service(
+            web::resource("/v1/employee")
+                .route(web::post().to(handlers::employee::create_employee))
+                .route(web::get().to(handlers::employee::get_employees)),
+        )
+        .service(
+            web::resource("/v1/employee/facets")
+                .route(web::get().to(handlers::stats::employee_facets)),
+        )
+        .service(
+            web::resource("/v1/employee/{identity_number}")
+                .route(web::patch().to(handlers::employee::update_employee))
+                .route(web::delete().to(handlers::employee::delete_employee)),
+        )
+        .service(
+            web::resource("/v1/department")
+                .route(web::post().to(handlers::department::create_department))
+                .route(web::get().to(handlers::department::get_departments)),
+        )
+        .service(
+            web::resource("/v1/department/with-employees")
+                .route(web::get().to(handlers::department::get_departments_with_employees)),
+        )
+        .service(
+            web::resource("/v1/department/count")
+                .route(web::get().to(handlers::department::count_departments)),
+        )
+        .service(
+            web::resource("/v1/department/by-name/{name}")
+                .route(web::get().to(handlers::department::get_department_by_name)),
+        )
+        .service(
+            web::resource("/v1/department/by-code/{code}")
+                .route(web::get().to(handlers::department::get_department_by_code)),
+        )
+        .service(
+            web::resource("/v1/department/{department_id}/delete-with-reassign")
+                .route(web::post().to(handlers::department::delete_department_with_reassign)),
+        )
+        .service(
+            web::resource("/v1/department/{department_id}/archive")
+                .route(web::post().to(handlers::department::archive_department)),
+        )
+        .service(
+            web::resource("/v1/department/{department_id}/unarchive")
+                .route(web::post().to(handlers::department::unarchive_department)),
+        )
+        .service(
+            web::resource("/v1/department/{department_id}/stats")
+                .route(web::get().to(handlers::stats::department_stats)),
+        )
+        .service(
+            web::resource("/v1/department/{department_id}")
+                .route(web::patch().to(handlers::department::update_department))
+                .route(web::delete().to(handlers::department::delete_department)),
+        )
+        .service(
+            web::resource("/v1/stats/employees-by-department")
+                .route(web::get().to(handlers::stats::employees_by_department)),
+        )
+        .service(
+            web::resource("/v1/stats/tenure")
+                .route(web::get().to(handlers::stats::tenure)),
+        )
+        .service(
+            web::resource("/v1/admin/users")
+                .route(web::post().to(handlers::admin::provision_user)),
+        )
+        .service(
+            web::resource("/v1/admin/users/{user_id}/role")
+                .route(web::patch().to(handlers::admin::change_user_role)),
+        )
+        .service(
+            web::resource("/v1/admin/users/{user_id}/reset-password")
+                .route(web::post().to(handlers::admin::reset_user_password)),
+        )
+        .service(
+            web::resource("/v1/admin/failed-logins")
+                .route(web::get().to(handlers::admin::get_failed_logins)),
+        )
+        .service(
+            web::resource("/v1/admin/employees/orphaned")
+                .route(web::get().to(handlers::admin::get_orphaned_employees)),
+        )
+        .service(
+            web::resource("/v1/admin/export")
+                .route(web::get().to(handlers::admin::export_data)),
+        )
+        .service(
+            web::resource("/v1/admin/import")
+                // Dumps are far larger than regular request bodies
+                .app_data(web::JsonConfig::default().limit(max_import_body_size))
+                .route(web::post().to(handlers::admin::import_data)),
+        )
+        .service(
+            web::resource("/v1/admin/maintenance")
+                .route(web::get().to(handlers::admin::get_maintenance_mode))
+                .route(web::put().to(handlers::admin::set_maintenance_mode)),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use uuid::Uuid;
+    use crate::test_support::{test_app, test_pool};
+    use crate::utils::jwt;
+
+    // Without a token every matched route answers 401, so anything else means it didn't match
+
+    #[actix_web::test]
+    async fn resolves_paths_with_and_without_a_trailing_slash() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        for uri in ["/v1/user", "/v1/user/"] {
+            let res = test::call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+        }
+    }
+
+    #[actix_web::test]
+    async fn matches_parameterized_routes_after_trimming() {
+        let app = test::init_service(test_app(test_pool())).await;
+        let department_id = Uuid::new_v4();
+
+        let requests = [
+            TestRequest::delete().uri(&format!("/v1/department/{}/", department_id)),
+            TestRequest::get().uri(&format!("/v1/department/{}/stats/", department_id)),
+        ];
+        for req in requests {
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", res.request().path());
+        }
+    }
+
+    #[actix_web::test]
+    async fn malformed_authorization_headers_are_rejected_like_a_missing_one() {
+        let app = test::init_service(test_app(test_pool())).await;
+        let authorizations = [None, Some(""), Some("   "), Some("Bearer"), Some("Bearer "), Some("Token abc"), Some("abc"), Some("Bearer a b")];
+
+        for uri in ["/v1/user", "/v1/employee", "/v1/department", "/v1/file"] {
+            for authorization in authorizations {
+                let mut req = TestRequest::get().uri(uri);
+                if let Some(value) = authorization {
+                    req = req.insert_header((header::AUTHORIZATION, value));
+                }
+                let res = test::call_service(&app, req.to_request()).await;
+                assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{} with {:?}", uri, authorization);
+
+                let body = test::read_body(res).await;
+                let body = String::from_utf8_lossy(&body);
+                assert!(body.contains(jwt::MISSING_TOKEN_MESSAGE), "{} with {:?}: {}", uri, authorization, body);
+            }
+        }
+    }
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use actix_web::http::header::HeaderName;
+use sqlx::postgres::PgConnectOptions;
+use crate::db;
+
+/// Returns `true` when the variable is set to `1`, `true`, `yes` or `on`.
+pub fn env_flag(name: &str) -> bool {
+    env::var(name).map(|value| is_truthy(&value)).unwrap_or(false)
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+/// Parses the variable, falling back to `default` when it is unset or invalid.
+pub fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// One year, the usual HSTS preload minimum
+pub const DEFAULT_HSTS_MAX_AGE: u64 = 31_536_000;
+
+/// Default sort for listings, set with `ORDER_DEFAULT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListOrder {
+    CreatedDesc,
+    NameAsc,
+}
+
+impl ListOrder {
+    /// `ORDER BY` clause for a table, with the primary key breaking ties so offset
+    /// pages never skip or repeat rows.
+    pub fn order_by(self, primary_key: &str) -> String {
+        match self {
+            ListOrder::CreatedDesc => format!(" ORDER BY created_at DESC, {} DESC", primary_key),
+            ListOrder::NameAsc => format!(" ORDER BY name ASC, {} ASC", primary_key),
+        }
+    }
+}
+
+impl FromStr for ListOrder {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "created_desc" => Ok(ListOrder::CreatedDesc),
+            "name_asc" => Ok(ListOrder::NameAsc),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Body of error responses, set with `ERROR_FORMAT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// `{ "error": ... }`
+    Simple,
+    /// RFC 7807 `application/problem+json`
+    Problem,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "simple" => Ok(ErrorFormat::Simple),
+            "problem" => Ok(ErrorFormat::Problem),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Response timestamp format, set with `DATE_FORMAT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateFormat {
+    /// chrono's RFC 3339 output, with as many fractional digits as the value has
+    Rfc3339,
+    /// RFC 3339 truncated to milliseconds
+    Rfc3339Millis,
+    /// Whole seconds since the epoch
+    Unix,
+}
+
+impl FromStr for DateFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "rfc3339" => Ok(DateFormat::Rfc3339),
+            "rfc3339-millis" => Ok(DateFormat::Rfc3339Millis),
+            "unix" => Ok(DateFormat::Unix),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Settings validated once at startup, so misconfiguration fails fast with every problem
+/// listed together instead of surfacing on the first request that needs them.
+#[derive(Clone)]
+pub struct Config {
+    /// Built from `DATABASE_URL` plus any `PGSSLMODE`/`PGSSLROOTCERT` overrides
+    pub database: PgConnectOptions,
+    pub s3_bucket: String,
+    pub aws_region: Option<String>,
+    pub bind_address: SocketAddr,
+    pub max_json_body_size: usize,
+    pub max_import_body_size: usize,
+    pub max_request_body_size: usize,
+    pub request_timeout: Duration,
+    pub max_concurrent_uploads_per_user: usize,
+    pub default_order: ListOrder,
+    pub db_min_connections: u32,
+    pub multipart_max_parts: usize,
+    pub multipart_max_field_name_len: usize,
+    pub multipart_max_total_size: usize,
+    /// Accepted names for the multipart file part, for clients that send `image` or `upload`
+    pub upload_field_names: Vec<String>,
+    /// Requests per window by route pattern; rate limiting is off when empty
+    pub rate_limits: HashMap<String, u32>,
+    pub rate_limit_window: Duration,
+    /// Reject requests the proxy forwarded over plain HTTP, and send HSTS on the rest
+    pub require_https: bool,
+    /// Header the TLS-terminating proxy sets to the original scheme
+    pub forwarded_proto_header: HeaderName,
+    pub hsts_max_age: u64,
+    /// Whether maintenance mode is on at startup; admins can toggle it at runtime
+    pub maintenance_mode: bool,
+    /// `Retry-After` seconds sent with maintenance rejections
+    pub maintenance_retry_after: u64,
+    pub cors_max_age: usize,
+    pub max_presigned_uploads: usize,
+    pub presign_expiry: Duration,
+    pub max_presigned_upload_size: u64,
+    /// Take the client IP from `Forwarded`/`X-Forwarded-For` instead of the socket; only
+    /// safe behind a proxy that overwrites those headers
+    pub trust_proxy_headers: bool,
+    pub error_format: ErrorFormat,
+    pub date_format: DateFormat,
+    /// Replace the detail of server errors with a generic message; it is still logged
+    pub production_errors: bool,
+}
+
+impl Config {
+    /// Reads every setting, returning all missing or invalid variables at once.
+    pub fn from_env() -> Result<Self, Vec<String>> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Like `from_env`, but looks each variable up with `var`, so tests can build a config
+    /// without touching the process environment.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, Vec<String>> {
+        let mut vars = Vars { var: &var, errors: Vec::new() };
+
+        // Only checked here; tokens are signed and verified by `utils::jwt`
+        vars.required("JWT_SECRET");
+
+        let database_url = vars.required("DATABASE_URL");
+        let database = db::connect_options(&database_url, vars.get("PGSSLMODE"), vars.get("PGSSLROOTCERT"))
+            .unwrap_or_else(|database_errors| {
+                // A missing DATABASE_URL has already been reported
+                vars.errors.extend(
+                    database_errors
+                        .into_iter()
+                        .filter(|err| !database_url.is_empty() || !err.starts_with("DATABASE_URL")),
+                );
+                PgConnectOptions::new()
+            });
+
+        let config = Config {
+            database,
+            s3_bucket: vars.required("AWS_S3_BUCKET"),
+            aws_region: vars.get("AWS_REGION").filter(|region| !region.trim().is_empty()),
+            bind_address: vars.parsed("BIND_ADDRESS", SocketAddr::from(([127, 0, 0, 1], 8080))),
+            // Applies to the decompressed size of gzip JSON bodies
+            max_json_body_size: vars.parsed("MAX_JSON_BODY_SIZE", 262_144),
+            max_import_body_size: vars.parsed("MAX_IMPORT_BODY_SIZE", 52_428_800),
+            max_request_body_size: vars.parsed("MAX_REQUEST_BODY_SIZE", 2_097_152),
+            request_timeout: Duration::from_secs(vars.parsed("REQUEST_TIMEOUT_SECONDS", 30)),
+            // Zero would reject every upload with 429
+            max_concurrent_uploads_per_user: vars.nonzero("MAX_CONCURRENT_UPLOADS_PER_USER", 2),
+            default_order: vars.parsed("ORDER_DEFAULT", ListOrder::CreatedDesc),
+            db_min_connections: vars.parsed("DB_MIN_CONNECTIONS", 0),
+            multipart_max_parts: vars.parsed("MULTIPART_MAX_PARTS", 4),
+            multipart_max_field_name_len: vars.parsed("MULTIPART_MAX_FIELD_NAME_LEN", 64),
+            multipart_max_total_size: vars.parsed("MULTIPART_MAX_TOTAL_SIZE", 1_048_576),
+            upload_field_names: upload_field_names(&vars),
+            rate_limits: rate_limits(&mut vars),
+            // A zero window expires immediately, which would silently disable limiting
+            rate_limit_window: Duration::from_secs(vars.nonzero("RATE_LIMIT_WINDOW_SECONDS", 60)),
+            require_https: vars.flag("REQUIRE_HTTPS"),
+            forwarded_proto_header: vars.parsed("FORWARDED_PROTO_HEADER", HeaderName::from_static("x-forwarded-proto")),
+            hsts_max_age: vars.parsed("HSTS_MAX_AGE", DEFAULT_HSTS_MAX_AGE),
+            maintenance_mode: vars.flag("MAINTENANCE_MODE"),
+            maintenance_retry_after: vars.parsed("MAINTENANCE_RETRY_AFTER", 120),
+            cors_max_age: vars.parsed("CORS_MAX_AGE", 3600),
+            max_presigned_uploads: vars.nonzero("MAX_PRESIGNED_UPLOADS", 10),
+            presign_expiry: Duration::from_secs(presign_expiry_seconds(&mut vars)),
+            max_presigned_upload_size: vars.nonzero("MAX_PRESIGNED_UPLOAD_SIZE", 10_485_760),
+            trust_proxy_headers: vars.flag("TRUST_PROXY_HEADERS"),
+            date_format: vars.parsed("DATE_FORMAT", DateFormat::Rfc3339),
+            error_format: vars.parsed("ERROR_FORMAT", ErrorFormat::Simple),
+            production_errors: vars.flag("PRODUCTION_ERRORS"),
+        };
+
+        if vars.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(vars.errors)
+        }
+    }
+}
+
+/// Variable lookup for `Config::from_vars`, collecting every problem it finds.
+struct Vars<'a> {
+    var: &'a dyn Fn(&str) -> Option<String>,
+    errors: Vec<String>,
+}
+
+impl Vars<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.var)(name)
+    }
+
+    /// Like `env_flag`.
+    fn flag(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|value| is_truthy(&value))
+    }
+
+    fn required(&mut self, name: &str) -> String {
+        match self.get(name) {
+            Some(value) if !value.trim().is_empty() => value,
+            Some(_) => {
+                self.errors.push(format!("{} must not be empty", name));
+                String::new()
+            }
+            None => {
+                self.errors.push(format!("{} must be set", name));
+                String::new()
+            }
+        }
+    }
+
+    /// Like `env_or`, but a value that is set and fails to parse is reported instead of ignored.
+    fn parsed<T: FromStr>(&mut self, name: &str, default: T) -> T {
+        match self.get(name) {
+            Some(value) => value.trim().parse().unwrap_or_else(|_| {
+                self.errors.push(format!("{} has an invalid value: {:?}", name, value));
+                default
+            }),
+            None => default,
+        }
+    }
+
+    /// Like `parsed`, but a value of zero is reported as well.
+    fn nonzero<T: FromStr + Default + PartialEq>(&mut self, name: &str, default: T) -> T {
+        let value = self.parsed(name, default);
+        if value == T::default() {
+            self.errors.push(format!("{} must be greater than 0", name));
+        }
+        value
+    }
+}
+
+/// S3 rejects presigned URLs that are valid for longer than seven days
+const MAX_PRESIGN_EXPIRY_SECONDS: u64 = 604_800;
+
+fn presign_expiry_seconds(vars: &mut Vars) -> u64 {
+    let seconds = vars.nonzero("PRESIGN_EXPIRY_SECONDS", 900);
+    if seconds > MAX_PRESIGN_EXPIRY_SECONDS {
+        vars.errors.push(format!("PRESIGN_EXPIRY_SECONDS must be at most {} (7 days)", MAX_PRESIGN_EXPIRY_SECONDS));
+    }
+    seconds
+}
+
+/// `UPLOAD_FIELD_NAME` is a comma-separated list such as `file,image`; `file` when unset.
+fn upload_field_names(vars: &Vars) -> Vec<String> {
+    let names = vars
+        .get("UPLOAD_FIELD_NAME")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    if names.is_empty() {
+        vec!["file".to_string()]
+    } else {
+        names
+    }
+}
+
+/// `RATE_LIMITS` is a JSON map of route pattern to requests per window, e.g.
+/// `{"/v1/file": 10, "/v1/auth": 20}`.
+fn rate_limits(vars: &mut Vars) -> HashMap<String, u32> {
+    let limits: HashMap<String, u32> = match vars.get("RATE_LIMITS") {
+        Some(raw) => serde_json::from_str(&raw).unwrap_or_else(|err| {
+            vars.errors.push(format!("RATE_LIMITS must be a JSON object of route to request count: {}", err));
+            HashMap::new()
+        }),
+        None => HashMap::new(),
+    };
+
+    let mut zero_routes = limits
+        .iter()
+        .filter(|(_, &limit)| limit == 0)
+        .map(|(route, _)| route.as_str())
+        .collect::<Vec<_>>();
+    if !zero_routes.is_empty() {
+        zero_routes.sort_unstable();
+        vars.errors.push(format!("RATE_LIMITS must be greater than 0 for: {}", zero_routes.join(", ")));
+    }
+
+    limits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn errors_for(vars: &[(&str, Option<&str>)]) -> Vec<String> {
+        match test_support::config_from(vars) {
+            Ok(_) => panic!("expected {:?} to be rejected", vars),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn list_orders_break_ties_on_the_primary_key() {
+        assert_eq!(ListOrder::CreatedDesc.order_by("employee_id"), " ORDER BY created_at DESC, employee_id DESC");
+        assert_eq!(ListOrder::NameAsc.order_by("department_id"), " ORDER BY name ASC, department_id ASC");
+    }
+
+    #[test]
+    fn loads_with_only_required_variables() {
+        let config = test_support::config_from(&[]).expect("test variables are valid");
+        assert_eq!(config.s3_bucket, "test-bucket");
+        assert_eq!(config.rate_limit_window, Duration::from_secs(60));
+        assert_eq!(config.hsts_max_age, DEFAULT_HSTS_MAX_AGE);
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let errors = errors_for(&[
+            ("DATABASE_URL", None),
+            ("AWS_S3_BUCKET", Some(" ")),
+            ("BIND_ADDRESS", Some("bad")),
+        ]);
+
+        assert_eq!(errors, vec![
+            "DATABASE_URL must be set".to_string(),
+            "AWS_S3_BUCKET must not be empty".to_string(),
+            "BIND_ADDRESS has an invalid value: \"bad\"".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn rejects_zero_limits() {
+        let errors = errors_for(&[
+            ("MAX_CONCURRENT_UPLOADS_PER_USER", Some("0")),
+            ("RATE_LIMIT_WINDOW_SECONDS", Some("0")),
+            ("RATE_LIMITS", Some(r#"{"/v1/file": 0, "/v1/auth": 20}"#)),
+        ]);
+
+        assert!(errors.contains(&"MAX_CONCURRENT_UPLOADS_PER_USER must be greater than 0".to_string()));
+        assert!(errors.contains(&"RATE_LIMIT_WINDOW_SECONDS must be greater than 0".to_string()));
+        assert!(errors.contains(&"RATE_LIMITS must be greater than 0 for: /v1/file".to_string()));
+    }
+
+    #[test]
+    fn parses_the_error_format() {
+        let config = test_support::config_from(&[("ERROR_FORMAT", Some("Problem"))]).unwrap();
+        assert_eq!(config.error_format, ErrorFormat::Problem);
+
+        let errors = errors_for(&[("ERROR_FORMAT", Some("xml"))]);
+        assert_eq!(errors, vec!["ERROR_FORMAT has an invalid value: \"xml\"".to_string()]);
+    }
+
+    #[test]
+    fn parses_the_date_format() {
+        let config = test_support::config_from(&[("DATE_FORMAT", Some("RFC3339-millis"))]).unwrap();
+        assert_eq!(config.date_format, DateFormat::Rfc3339Millis);
+
+        let errors = errors_for(&[("DATE_FORMAT", Some("iso"))]);
+        assert_eq!(errors, vec!["DATE_FORMAT has an invalid value: \"iso\"".to_string()]);
+    }
+
+    #[test]
+    fn splits_upload_field_names() {
+        let config = test_support::config_from(&[("UPLOAD_FIELD_NAME", Some(" file, image ,,upload"))]).unwrap();
+        assert_eq!(config.upload_field_names, ["file", "image", "upload"]);
+
+        let config = test_support::config_from(&[("UPLOAD_FIELD_NAME", Some(" , "))]).unwrap();
+        assert_eq!(config.upload_field_names, ["file"]);
+    }
+
+    #[test]
+    fn caps_presign_expiry_at_seven_days() {
+        let errors = errors_for(&[("PRESIGN_EXPIRY_SECONDS", Some("604801"))]);
+        assert_eq!(errors, vec!["PRESIGN_EXPIRY_SECONDS must be at most 604800 (7 days)".to_string()]);
+    }
+}
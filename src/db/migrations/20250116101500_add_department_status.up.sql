@@ -0,0 +1,3 @@
+ALTER TABLE departments
+    ADD COLUMN status VARCHAR NOT NULL DEFAULT 'active'
+    CHECK (status IN ('active', 'archived'));
@@ -0,0 +1,3 @@
+ALTER TABLE employees
+    ADD COLUMN employment_status VARCHAR NOT NULL DEFAULT 'active'
+    CHECK (employment_status IN ('active', 'on_leave', 'terminated'));
@@ -0,0 +1 @@
+ALTER TABLE employees ADD COLUMN hire_date DATE;
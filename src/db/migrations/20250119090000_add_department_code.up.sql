@@ -0,0 +1,24 @@
+ALTER TABLE departments ADD COLUMN code VARCHAR;
+
+-- Backfill existing departments, suffixing duplicates in creation order
+WITH slugs AS (
+    SELECT
+        department_id,
+        COALESCE(NULLIF(TRIM(BOTH '-' FROM LOWER(REGEXP_REPLACE(name, '[^a-zA-Z0-9]+', '-', 'g'))), ''), 'department') AS slug,
+        created_at
+    FROM departments
+),
+ranked AS (
+    SELECT
+        department_id,
+        slug,
+        ROW_NUMBER() OVER (PARTITION BY slug ORDER BY created_at, department_id) AS position
+    FROM slugs
+)
+UPDATE departments d
+SET code = CASE WHEN r.position = 1 THEN r.slug ELSE r.slug || '-' || r.position END
+FROM ranked r
+WHERE d.department_id = r.department_id;
+
+ALTER TABLE departments ALTER COLUMN code SET NOT NULL;
+CREATE UNIQUE INDEX idx_departments_code ON departments(code);
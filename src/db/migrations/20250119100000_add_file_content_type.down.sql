@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_files_user_id_created_at;
+ALTER TABLE files DROP COLUMN IF EXISTS content_type;
@@ -0,0 +1,12 @@
+ALTER TABLE files ADD COLUMN content_type VARCHAR;
+
+-- Existing uploads were stored under an extension derived from their detected type
+UPDATE files
+SET content_type = CASE
+    WHEN uri ILIKE '%.png' THEN 'image/png'
+    WHEN uri ILIKE '%.jpg' OR uri ILIKE '%.jpeg' THEN 'image/jpeg'
+    ELSE 'application/octet-stream'
+END;
+
+ALTER TABLE files ALTER COLUMN content_type SET NOT NULL;
+CREATE INDEX idx_files_user_id_created_at ON files(user_id, created_at);
@@ -0,0 +1,8 @@
+CREATE TABLE failed_logins (
+    failed_login_id BIGSERIAL PRIMARY KEY,
+    email VARCHAR NOT NULL,
+    source_ip VARCHAR,
+    attempted_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_failed_logins_attempted_at ON failed_logins(attempted_at);
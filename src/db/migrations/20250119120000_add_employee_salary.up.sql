@@ -0,0 +1,2 @@
+-- Salary in minor currency units (e.g. cents)
+ALTER TABLE employees ADD COLUMN salary BIGINT CHECK (salary >= 0);
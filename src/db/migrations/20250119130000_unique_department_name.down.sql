@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_departments_name_unique;
@@ -0,0 +1,16 @@
+-- Renames could previously create duplicate names. Renaming them here could collide with
+-- other existing names, so stop with the conflicting names and let an operator resolve them.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(quote_literal(name), ', ' ORDER BY name)
+    INTO duplicates
+    FROM (SELECT name FROM departments GROUP BY name HAVING COUNT(*) > 1) AS conflicts;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'Duplicate department names must be renamed before adding the unique index: %', duplicates;
+    END IF;
+END $$;
+
+CREATE UNIQUE INDEX idx_departments_name_unique ON departments(name);
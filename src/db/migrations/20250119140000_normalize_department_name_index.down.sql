@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_departments_name_normalized;
+CREATE UNIQUE INDEX idx_departments_name_unique ON departments(name);
@@ -0,0 +1,24 @@
+-- Names now collide ignoring case and surrounding/repeated whitespace. Suffixing existing
+-- collisions could clash with other names, so stop with the conflicting groups and let an
+-- operator resolve them.
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(names, '; ' ORDER BY names)
+    INTO duplicates
+    FROM (
+        SELECT string_agg(quote_literal(name), ', ' ORDER BY created_at, department_id) AS names
+        FROM departments
+        GROUP BY LOWER(REGEXP_REPLACE(TRIM(name), '\s+', ' ', 'g'))
+        HAVING COUNT(*) > 1
+    ) AS conflicts;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'Department names that differ only in case or whitespace must be renamed before adding the unique index: %', duplicates;
+    END IF;
+END $$;
+
+DROP INDEX IF EXISTS idx_departments_name_unique;
+CREATE UNIQUE INDEX idx_departments_name_normalized
+    ON departments (LOWER(REGEXP_REPLACE(TRIM(name), '\s+', ' ', 'g')));
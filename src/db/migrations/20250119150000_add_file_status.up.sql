@@ -0,0 +1,3 @@
+-- Presigned uploads are recorded as pending until the client confirms the object landed in S3
+ALTER TABLE files ADD COLUMN status VARCHAR NOT NULL DEFAULT 'confirmed'
+    CHECK (status IN ('pending', 'confirmed'));
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_employees_identity_number_pattern;
+DROP INDEX IF EXISTS idx_departments_name_trgm;
+DROP INDEX IF EXISTS idx_employees_name_trgm;
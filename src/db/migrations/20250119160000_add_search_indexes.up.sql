@@ -0,0 +1,14 @@
+-- Indexes for the employee and department listing filters. Plain B-tree indexes on
+-- employees(identity_number), employees(department_id) and departments(name) already
+-- exist from the initial migration; these cover the LIKE/ILIKE searches they can't serve.
+-- `gender` is left unindexed: with a handful of values a scan is as cheap as an index.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- `name LIKE '%...%'` and `name ILIKE '%...%'` match anywhere in the name, which only a
+-- trigram index can answer
+CREATE INDEX idx_employees_name_trgm ON employees USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_departments_name_trgm ON departments USING GIN (name gin_trgm_ops);
+
+-- `identity_number LIKE '...%'` is a prefix search; the default B-tree only serves it
+-- under the C collation, so add one with pattern operators
+CREATE INDEX idx_employees_identity_number_pattern ON employees(identity_number varchar_pattern_ops);
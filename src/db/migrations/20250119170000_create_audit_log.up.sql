@@ -0,0 +1,11 @@
+-- Security-relevant admin actions, written in the same transaction as the change itself
+CREATE TABLE audit_log (
+    audit_log_id BIGSERIAL PRIMARY KEY,
+    actor_id UUID NOT NULL,
+    action VARCHAR NOT NULL,
+    target_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_audit_log_target_id ON audit_log(target_id, created_at);
@@ -0,0 +1,2 @@
+-- Set when an admin resets a password with `forceChange`, and reported at login
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
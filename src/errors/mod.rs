@@ -1,12 +1,14 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use serde::Serialize;
 use std::fmt;
+use crate::config::ErrorFormat;
 // use log::error;
 
 #[derive(Debug)]
 pub enum AppError {
     NotFound(String),
     Unauthorized(String),
+    Forbidden(String),
     Conflict(String),
     InternalServerError(String),
     DatabaseError(String),
@@ -20,11 +22,74 @@ struct ErrorResponse {
     error: String,
 }
 
+/// RFC 7807 problem details body, emitted for `ErrorFormat::Problem`
+#[derive(Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: String,
+    status: u16,
+    detail: String,
+}
+
+impl AppError {
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::NotFound(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::Conflict(msg)
+            | AppError::InternalServerError(msg)
+            | AppError::DatabaseError(msg)
+            | AppError::AWSError(msg)
+            | AppError::JwtError(msg)
+            | AppError::BadRequest(msg) => msg,
+        }
+    }
+}
+
+/// Shared mapping for database errors that handlers don't treat specially
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => AppError::NotFound("Resource not found".to_string()),
+            err => AppError::DatabaseError(err.to_string()),
+        }
+    }
+}
+
+/// Body message for server errors when `PRODUCTION_ERRORS` hides their detail
+pub const GENERIC_SERVER_ERROR: &str = "Internal server error";
+
+/// A 500 for handlers built on actix's error helpers. The full detail is always logged;
+/// with `PRODUCTION_ERRORS`, `middleware::errors` replaces it in the body.
+pub fn internal_error<E: fmt::Debug + fmt::Display>(err: E) -> actix_web::Error {
+    log::error!("Internal error: {:?}", err);
+    actix_web::error::ErrorInternalServerError(err.to_string())
+}
+
+/// An error body in `format`. `AppError` responses are built as `ErrorFormat::Simple`;
+/// `middleware::errors` re-renders them when the app is configured otherwise.
+pub fn error_body(status: StatusCode, message: &str, format: ErrorFormat) -> HttpResponse {
+    match format {
+        ErrorFormat::Simple => HttpResponse::build(status).json(ErrorResponse { error: message.to_string() }),
+        ErrorFormat::Problem => HttpResponse::build(status)
+            .content_type("application/problem+json")
+            .json(ProblemDetails {
+                problem_type: "about:blank".to_string(),
+                title: status.canonical_reason().unwrap_or("Error").to_string(),
+                status: status.as_u16(),
+                detail: message.to_string(),
+            }),
+    }
+}
+
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AppError::NotFound(msg) => write!(f, "Not Found: {}", msg),
             AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::InternalServerError(msg) => write!(f, "Internal Server Error: {}", msg),
             AppError::DatabaseError(msg) => write!(f, "Database Error: {}", msg),
@@ -36,16 +101,21 @@ impl fmt::Display for AppError {
 }
 
 impl ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
+    fn status_code(&self) -> StatusCode {
         match self {
-            AppError::NotFound(msg) => HttpResponse::NotFound().json(ErrorResponse { error: msg.clone() }),
-            AppError::Unauthorized(msg) => HttpResponse::Unauthorized().json(ErrorResponse { error: msg.clone() }),
-            AppError::Conflict(msg) => HttpResponse::Conflict().json(ErrorResponse { error: msg.clone() }),
-            AppError::InternalServerError(msg) => HttpResponse::InternalServerError().json(ErrorResponse { error: msg.clone() }),
-            AppError::DatabaseError(msg) => HttpResponse::InternalServerError().json(ErrorResponse { error: msg.clone() }),
-            AppError::AWSError(msg) => HttpResponse::InternalServerError().json(ErrorResponse { error: msg.clone() }),
-            AppError::JwtError(msg) => HttpResponse::Unauthorized().json(ErrorResponse { error: msg.clone() }),
-            AppError::BadRequest(msg) => HttpResponse::BadRequest().json(ErrorResponse { error: msg.clone() }),
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::AWSError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::JwtError(_) => StatusCode::UNAUTHORIZED,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
         }
     }
-}
\ No newline at end of file
+
+    fn error_response(&self) -> HttpResponse {
+        error_body(self.status_code(), self.message(), ErrorFormat::Simple)
+    }
+}
@@ -0,0 +1,821 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use argon2::{Argon2, password_hash::PasswordHasher, password_hash::SaltString};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::{Rng, RngCore};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+use log::{error, info};
+use crate::db;
+use crate::handlers::auth::peppered_password;
+use crate::utils;
+use crate::utils::json_stream::{spawn_json_stream, JsonStreamWriter};
+use crate::errors::AppError;
+use crate::middleware::maintenance::MaintenanceMode;
+use crate::models::employee::Employee;
+use crate::models::audit::{ACTION_PASSWORD_RESET, ACTION_ROLE_CHANGED};
+use crate::models::failed_login::FailedLogin;
+use crate::models::user::{ROLES, ROLE_ADMIN, ROLE_MEMBER};
+use crate::models::export::{DataExport, ExportedDepartment, ExportedEmployee, ExportedUser};
+
+#[derive(Deserialize)]
+pub struct MaintenanceToggle {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct FailedLoginQueryParams {
+    email: Option<String>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ProvisionUser {
+    #[validate(email)]
+    email: String,
+    #[validate(length(min = 8, max = 32))]
+    password: String,
+    role: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RoleChange {
+    role: String,
+}
+
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordReset {
+    /// A temporary password is generated when omitted
+    #[validate(length(min = 8, max = 32))]
+    password: Option<String>,
+    /// Report `mustChangePassword` at the user's next login
+    #[serde(default)]
+    force_change: bool,
+}
+
+/// Length of generated temporary passwords
+const TEMPORARY_PASSWORD_LEN: usize = 16;
+
+/// Caps a single page of failed logins
+const MAX_FAILED_LOGINS: i64 = 1000;
+
+pub async fn get_maintenance_mode(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    maintenance: web::Data<MaintenanceMode>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::require_admin(&req, &pool).await?;
+
+    Ok(HttpResponse::Ok().json(json!({ "enabled": maintenance.is_enabled() })))
+}
+
+pub async fn set_maintenance_mode(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    maintenance: web::Data<MaintenanceMode>,
+    toggle: web::Json<MaintenanceToggle>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+
+    maintenance.set(toggle.enabled);
+    info!("Maintenance mode set to {} by {}", toggle.enabled, claims.sub);
+
+    Ok(HttpResponse::Ok().json(json!({ "enabled": toggle.enabled })))
+}
+
+/// Creates a user on behalf of an admin, for deployments that turn off public signup
+/// with `ALLOW_SIGNUP=false`. The role defaults to `member`.
+pub async fn provision_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    new_user: web::Json<ProvisionUser>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+
+    new_user.validate().map_err(|err| {
+        let locale = utils::i18n::Locale::from_request(&req);
+        AppError::BadRequest(utils::i18n::format_validation_errors(&err, locale))
+    })?;
+
+    let role = new_user.role.as_deref().unwrap_or(ROLE_MEMBER);
+    if !ROLES.contains(&role) {
+        return Err(AppError::BadRequest(format!("role must be one of: {}", ROLES.join(", "))).into());
+    }
+
+    let email_exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = LOWER($1))",
+        new_user.email
+    )
+    .fetch_one(&**pool)
+    .await
+    .map_err(AppError::from)?;
+
+    if email_exists.unwrap_or(false) {
+        return Err(AppError::Conflict("Email already exists".to_string()).into());
+    }
+
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::default()
+        .hash_password(&peppered_password(&new_user.password), &salt)
+        .map_err(|_| AppError::InternalServerError("Hashing error".to_string()))?
+        .to_string();
+
+    let user_id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query!(
+        "INSERT INTO users (user_id, email, password, role, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6)",
+        user_id,
+        new_user.email,
+        password_hash,
+        role,
+        now,
+        now
+    )
+    .execute(&**pool)
+    .await
+    .map_err(AppError::from)?;
+
+    info!("User {} provisioned with role {} by {}", user_id, role, claims.sub);
+
+    Ok(HttpResponse::Created().json(json!({
+        "userId": user_id,
+        "email": new_user.email,
+        "role": role,
+    })))
+}
+
+/// Promotes or demotes a user. Admin rows are locked while the change is made so two
+/// concurrent demotions cannot leave the deployment without an admin. The change is
+/// recorded in `audit_log` in the same transaction.
+pub async fn change_user_role(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<String>,
+    change: web::Json<RoleChange>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+
+    let user_id = Uuid::parse_str(&user_id.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
+
+    let role = change.into_inner().role;
+    if !ROLES.contains(&role.as_str()) {
+        return Err(AppError::BadRequest(format!("role must be one of: {}", ROLES.join(", "))).into());
+    }
+
+    let actor_id = claims.user_id()?;
+    let role = db::with_transaction(&pool, move |tx| Box::pin(async move {
+        let admins = sqlx::query_scalar!(
+            "SELECT user_id FROM users WHERE role = $1 FOR UPDATE",
+            ROLE_ADMIN
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let previous_role = sqlx::query_scalar!(
+            "SELECT role FROM users WHERE user_id = $1 FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        if previous_role == ROLE_ADMIN && role != ROLE_ADMIN && admins == [user_id] {
+            return Err(AppError::Conflict("Cannot demote the last remaining admin".to_string()));
+        }
+
+        sqlx::query!(
+            "UPDATE users SET role = $1, updated_at = $2 WHERE user_id = $3",
+            role,
+            Utc::now(),
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if previous_role != role {
+            db::record_audit(
+                &mut **tx,
+                actor_id,
+                ACTION_ROLE_CHANGED,
+                user_id,
+                json!({ "from": previous_role, "to": role }),
+            )
+            .await?;
+        }
+
+        Ok::<_, AppError>(role)
+    }))
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "userId": user_id,
+        "role": role,
+    })))
+}
+
+/// Sets a user's password for them, either to the one provided or to a generated temporary
+/// password. A generated password is returned in this response only and never stored in
+/// plain text. With `forceChange` the account is flagged so login reports that the password
+/// must be changed. The reset is recorded in `audit_log` in the same transaction.
+pub async fn reset_user_password(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<String>,
+    reset: Option<web::Json<PasswordReset>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+
+    let user_id = Uuid::parse_str(&user_id.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid user ID".to_string()))?;
+
+    let (provided, force_change) = match reset {
+        Some(reset) => {
+            reset.validate().map_err(|err| {
+                let locale = utils::i18n::Locale::from_request(&req);
+                AppError::BadRequest(utils::i18n::format_validation_errors(&err, locale))
+            })?;
+            let reset = reset.into_inner();
+            (reset.password, reset.force_change)
+        }
+        None => (None, false),
+    };
+
+    let generated = provided.is_none();
+    let password = provided.unwrap_or_else(|| {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TEMPORARY_PASSWORD_LEN)
+            .map(char::from)
+            .collect()
+    });
+
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::default()
+        .hash_password(&peppered_password(&password), &salt)
+        .map_err(|_| AppError::InternalServerError("Hashing error".to_string()))?
+        .to_string();
+
+    let actor_id = claims.user_id()?;
+    db::with_transaction(&pool, move |tx| Box::pin(async move {
+        let updated = sqlx::query!(
+            "UPDATE users SET password = $1, must_change_password = $2, updated_at = $3 WHERE user_id = $4",
+            password_hash,
+            force_change,
+            Utc::now(),
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        db::record_audit(
+            &mut **tx,
+            actor_id,
+            ACTION_PASSWORD_RESET,
+            user_id,
+            json!({ "generated": generated, "forceChange": force_change }),
+        )
+        .await?;
+
+        Ok::<_, AppError>(())
+    }))
+    .await?;
+
+    if generated {
+        Ok(HttpResponse::Ok().json(json!({ "userId": user_id, "temporaryPassword": password })))
+    } else {
+        Ok(HttpResponse::Ok().json(json!({ "userId": user_id })))
+    }
+}
+
+/// Lists recent failed logins, newest first. `email` matches the masked form stored
+/// for each attempt, so `jo***@example.com` or just the domain both work.
+pub async fn get_failed_logins(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<FailedLoginQueryParams>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::require_admin(&req, &pool).await?;
+
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_FAILED_LOGINS);
+
+    let failed_logins = sqlx::query_as!(
+        FailedLogin,
+        r#"
+        SELECT failed_login_id, email, source_ip, attempted_at
+        FROM failed_logins
+        WHERE ($1::VARCHAR IS NULL OR email ILIKE '%' || $1 || '%')
+          AND ($2::TIMESTAMPTZ IS NULL OR attempted_at >= $2)
+        ORDER BY attempted_at DESC, failed_login_id DESC
+        LIMIT $3
+        "#,
+        query.email,
+        query.since,
+        limit
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to list failed logins: {:?}", err);
+        AppError::DatabaseError("Failed to list failed logins".to_string())
+    })?;
+
+    Ok(HttpResponse::Ok().json(failed_logins))
+}
+
+/// Lists employees whose department no longer exists. The foreign key prevents new
+/// orphans, so any rows here predate it or were loaded with constraints disabled.
+pub async fn get_orphaned_employees(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::require_admin(&req, &pool).await?;
+
+    let employees = sqlx::query_as!(
+        Employee,
+        r#"
+        SELECT e.employee_id, e.identity_number, e.name, e.employee_image_uri, e.gender,
+               e.department_id, e.employment_status, e.hire_date, e.salary, e.created_at, e.updated_at
+        FROM employees e
+        LEFT JOIN departments d ON d.department_id = e.department_id
+        WHERE d.department_id IS NULL
+        ORDER BY e.created_at, e.employee_id
+        "#
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to list orphaned employees: {:?}", err);
+        AppError::DatabaseError("Failed to list orphaned employees".to_string())
+    })?;
+
+    Ok(HttpResponse::Ok().json(employees))
+}
+
+/// Streams every user, department and employee as one JSON document.
+/// Rows are serialized as they are fetched so large datasets are never held in memory.
+pub async fn export_data(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+    info!("Data export started by {}", claims.sub);
+
+    let pool = pool.into_inner();
+    let body = spawn_json_stream("Data export", move |writer| async move {
+        write_export(&pool, &writer).await
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .streaming(body))
+}
+
+async fn write_export(pool: &PgPool, writer: &JsonStreamWriter) -> Result<(), String> {
+    writer.write_raw(b"{").await?;
+
+    let users = sqlx::query_as!(
+        ExportedUser,
+        r#"
+        SELECT user_id, email, name, user_image_uri, company_name, company_image_uri,
+               role, created_at, updated_at
+        FROM users
+        ORDER BY created_at, user_id
+        "#
+    )
+    .fetch(pool);
+    writer.write_array_field("users", users).await?;
+
+    writer.write_raw(b",").await?;
+    let departments = sqlx::query_as!(
+        ExportedDepartment,
+        r#"
+        SELECT department_id, name, code, status, created_at, updated_at
+        FROM departments
+        ORDER BY created_at, department_id
+        "#
+    )
+    .fetch(pool);
+    writer.write_array_field("departments", departments).await?;
+
+    writer.write_raw(b",").await?;
+    let employees = sqlx::query_as!(
+        ExportedEmployee,
+        r#"
+        SELECT employee_id, identity_number, name, employee_image_uri, gender,
+               department_id, employment_status, hire_date, salary, created_at, updated_at
+        FROM employees
+        ORDER BY created_at, employee_id
+        "#
+    )
+    .fetch(pool);
+    writer.write_array_field("employees", employees).await?;
+
+    writer.write_raw(b"}").await
+}
+
+/// Restores a dump produced by `export_data` inside a single transaction; any failure
+/// rolls back the whole import. Password hashes are not exported, so imported users get
+/// an unusable password and must have it reset before they can log in.
+pub async fn import_data(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    dump: web::Json<DataExport>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::require_admin(&req, &pool).await?;
+    let dump = dump.into_inner();
+
+    let placeholder_password = unusable_password_hash()?;
+    let (users, departments, employees) = (dump.users.len(), dump.departments.len(), dump.employees.len());
+
+    db::with_transaction(&pool, move |tx| Box::pin(async move {
+        for user in &dump.users {
+            sqlx::query!(
+                r#"
+                INSERT INTO users (user_id, email, password, name, user_image_uri, company_name,
+                                   company_image_uri, role, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                "#,
+                user.user_id,
+                user.email,
+                placeholder_password,
+                user.name,
+                user.user_image_uri,
+                user.company_name,
+                user.company_image_uri,
+                user.role,
+                user.created_at,
+                user.updated_at
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(import_error)?;
+        }
+
+        for department in &dump.departments {
+            sqlx::query!(
+                r#"
+                INSERT INTO departments (department_id, name, code, status, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+                department.department_id,
+                department.name,
+                department.code,
+                department.status,
+                department.created_at,
+                department.updated_at
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(import_error)?;
+        }
+
+        for employee in &dump.employees {
+            sqlx::query!(
+                r#"
+                INSERT INTO employees (employee_id, identity_number, name, employee_image_uri, gender,
+                                       department_id, employment_status, hire_date, salary, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+                employee.employee_id,
+                employee.identity_number,
+                employee.name,
+                employee.employee_image_uri,
+                employee.gender,
+                employee.department_id,
+                employee.employment_status,
+                employee.hire_date,
+                employee.salary,
+                employee.created_at,
+                employee.updated_at
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(import_error)?;
+        }
+
+        Ok::<_, AppError>(())
+    }))
+    .await?;
+
+    info!(
+        "Data import by {}: {} users, {} departments, {} employees",
+        claims.sub, users, departments, employees
+    );
+
+    Ok(HttpResponse::Ok().json(json!({
+        "users": users,
+        "departments": departments,
+        "employees": employees,
+    })))
+}
+
+/// Hashes a random secret that is immediately discarded, so no password verifies against it
+fn unusable_password_hash() -> Result<String, AppError> {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let salt = SaltString::generate(&mut rand::thread_rng());
+
+    Argon2::default()
+        .hash_password(&secret, &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| AppError::InternalServerError("Hashing error".to_string()))
+}
+
+/// Constraint violations mean the dump clashes with existing rows or references missing ones
+fn import_error(err: sqlx::Error) -> AppError {
+    match &err {
+        sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505") => {
+            AppError::Conflict(format!("Import conflicts with existing data: {}", db_err.message()))
+        }
+        sqlx::Error::Database(db_err) if db_err.code().is_some_and(|code| code.starts_with("23")) => {
+            AppError::BadRequest(format!("Invalid import data: {}", db_err.message()))
+        }
+        _ => {
+            error!("Database error during import: {:?}", err);
+            AppError::DatabaseError("Import failed".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::models::audit::{ACTION_PASSWORD_RESET, ACTION_ROLE_CHANGED};
+    use crate::models::user::{ROLE_ADMIN, ROLE_MEMBER};
+    use crate::test_support::{authed, set_env, test_app, test_pool, token_for, TestDb};
+
+    fn role_change(caller: Uuid, user_id: Uuid, role: &str) -> TestRequest {
+        authed(TestRequest::patch().uri(&format!("/v1/admin/users/{}/role", user_id)), &token_for(caller, ROLE_ADMIN))
+            .set_json(json!({ "role": role }))
+    }
+
+    async fn role_of(db: &TestDb, user_id: Uuid) -> String {
+        sqlx::query_scalar!("SELECT role FROM users WHERE user_id = $1", user_id)
+            .fetch_one(&db.pool)
+            .await
+            .expect("failed to read role")
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn admin_routes_trust_the_stored_role_over_the_token() {
+        let mut db = TestDb::new().await;
+        let member = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = authed(TestRequest::get().uri("/v1/admin/maintenance"), &token_for(member, ROLE_ADMIN)).to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn change_user_role_promotes_and_records_an_audit_entry() {
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let member = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let body: Value = test::call_and_read_body_json(&app, role_change(admin, member, ROLE_ADMIN).to_request()).await;
+
+        assert_eq!(body["role"], ROLE_ADMIN);
+        assert_eq!(role_of(&db, member).await, ROLE_ADMIN);
+        let entry = sqlx::query!(
+            "SELECT actor_id, action, details FROM audit_log WHERE target_id = $1",
+            member
+        )
+        .fetch_one(&db.pool)
+        .await
+        .expect("expected one audit entry");
+        assert_eq!(entry.actor_id, admin);
+        assert_eq!(entry.action, ACTION_ROLE_CHANGED);
+        assert_eq!(entry.details, json!({ "from": ROLE_MEMBER, "to": ROLE_ADMIN }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn change_user_role_demotion_revokes_admin_access_immediately() {
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let other_admin = db.seed_user(ROLE_ADMIN).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let res = test::call_service(&app, role_change(admin, other_admin, ROLE_MEMBER).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(role_of(&db, other_admin).await, ROLE_MEMBER);
+
+        // Their token still claims admin, but the stored role now decides
+        let res = test::call_service(&app, role_change(other_admin, admin, ROLE_MEMBER).to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(role_of(&db, admin).await, ROLE_ADMIN);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn change_user_role_refuses_to_demote_the_last_admin() {
+        let mut db = TestDb::exclusive().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        db.demote_other_admins(admin).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let res = test::call_service(&app, role_change(admin, admin, ROLE_MEMBER).to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        assert_eq!(role_of(&db, admin).await, ROLE_ADMIN);
+    }
+
+    fn password_reset(caller: Uuid, caller_role: &str, user_id: Uuid) -> TestRequest {
+        authed(
+            TestRequest::post().uri(&format!("/v1/admin/users/{}/reset-password", user_id)),
+            &token_for(caller, caller_role),
+        )
+    }
+
+    #[actix_web::test]
+    async fn reset_user_password_requires_a_token() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let req = TestRequest::post().uri(&format!("/v1/admin/users/{}/reset-password", Uuid::new_v4())).to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn reset_user_password_is_forbidden_for_members() {
+        let mut db = TestDb::new().await;
+        let member = db.seed_user(ROLE_MEMBER).await;
+        let other = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let res = test::call_service(&app, password_reset(member, ROLE_MEMBER, other).to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        let password = sqlx::query_scalar!("SELECT password FROM users WHERE user_id = $1", other)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(password, "unused");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn reset_user_password_generates_a_password_that_logs_in_and_flags_the_account() {
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let member = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = password_reset(admin, ROLE_ADMIN, member).set_json(json!({ "forceChange": true })).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let temporary_password = body["temporaryPassword"].as_str().expect("a generated password").to_string();
+        assert_eq!(temporary_password.len(), 16);
+
+        let login = TestRequest::post()
+            .uri("/v1/auth")
+            .set_json(json!({
+                "email": format!("{}@example.com", member),
+                "password": temporary_password,
+                "action": "login",
+            }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, login).await;
+        assert!(body["token"].is_string());
+        assert_eq!(body["mustChangePassword"], true);
+
+        let entry = sqlx::query!("SELECT actor_id, action, details FROM audit_log WHERE target_id = $1", member)
+            .fetch_one(&db.pool)
+            .await
+            .expect("expected one audit entry");
+        assert_eq!(entry.actor_id, admin);
+        assert_eq!(entry.action, ACTION_PASSWORD_RESET);
+        assert_eq!(entry.details, json!({ "generated": true, "forceChange": true }));
+    }
+
+    /// The rows of `dump`'s `table` whose `key` is one of `ids`.
+    fn rows_with(dump: &Value, table: &str, key: &str, ids: &[Uuid]) -> Vec<Value> {
+        dump[table]
+            .as_array()
+            .expect("export table is an array")
+            .iter()
+            .filter(|row| ids.iter().any(|id| row[key] == json!(id)))
+            .cloned()
+            .collect()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn export_then_import_restores_the_same_rows() {
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let member = db.seed_user(ROLE_MEMBER).await;
+        let department_id = db.seed_department(&format!("Export {}", Uuid::new_v4())).await;
+        let employee_id = db.seed_employee(department_id, "female", "on_leave").await;
+        sqlx::query!("UPDATE employees SET salary = 420000, hire_date = '2020-02-29' WHERE employee_id = $1", employee_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let export = || authed(TestRequest::get().uri("/v1/admin/export"), &token_for(admin, ROLE_ADMIN)).to_request();
+
+        // Only the seeded rows; the rest of the database is left alone
+        let dump: Value = test::call_and_read_body_json(&app, export()).await;
+        let seeded = json!({
+            "users": rows_with(&dump, "users", "user_id", &[member]),
+            "departments": rows_with(&dump, "departments", "department_id", &[department_id]),
+            "employees": rows_with(&dump, "employees", "employee_id", &[employee_id]),
+        });
+
+        sqlx::query!("DELETE FROM employees WHERE employee_id = $1", employee_id).execute(&db.pool).await.unwrap();
+        sqlx::query!("DELETE FROM departments WHERE department_id = $1", department_id).execute(&db.pool).await.unwrap();
+        sqlx::query!("DELETE FROM users WHERE user_id = $1", member).execute(&db.pool).await.unwrap();
+
+        let import = authed(TestRequest::post().uri("/v1/admin/import"), &token_for(admin, ROLE_ADMIN)).set_json(&seeded);
+        let counts: Value = test::call_and_read_body_json(&app, import.to_request()).await;
+        assert_eq!(counts, json!({ "users": 1, "departments": 1, "employees": 1 }));
+
+        let dump: Value = test::call_and_read_body_json(&app, export()).await;
+        assert_eq!(rows_with(&dump, "users", "user_id", &[member]), seeded["users"].as_array().unwrap().clone());
+        assert_eq!(rows_with(&dump, "departments", "department_id", &[department_id]), seeded["departments"].as_array().unwrap().clone());
+        assert_eq!(rows_with(&dump, "employees", "employee_id", &[employee_id]), seeded["employees"].as_array().unwrap().clone());
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn lists_employees_whose_department_is_missing() {
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let department_id = db.seed_department(&format!("Intact {}", Uuid::new_v4())).await;
+        let placed = db.seed_employee(department_id, "female", "active").await;
+
+        // Only possible with the foreign key bypassed, which needs a superuser
+        let missing_department = Uuid::new_v4();
+        db.track_department(missing_department);
+        let orphan = Uuid::new_v4();
+        let mut tx = db.pool.begin().await.unwrap();
+        sqlx::query("SET LOCAL session_replication_role = replica").execute(&mut *tx).await.unwrap();
+        sqlx::query(
+            "INSERT INTO employees (employee_id, identity_number, name, gender, department_id, created_at, updated_at) VALUES ($1, $2, 'Orphan', 'male', $3, NOW(), NOW())",
+        )
+        .bind(orphan)
+        .bind(orphan.simple().to_string())
+        .bind(missing_department)
+        .execute(&mut *tx)
+        .await
+        .expect("failed to seed an orphaned employee");
+        tx.commit().await.unwrap();
+
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let req = authed(TestRequest::get().uri("/v1/admin/employees/orphaned"), &token_for(admin, ROLE_ADMIN));
+        let employees: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+
+        let listed = employees
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|employee| employee["employee_id"].as_str().unwrap().parse::<Uuid>().unwrap())
+            .collect::<Vec<_>>();
+        assert!(listed.contains(&orphan), "{}", employees);
+        assert!(!listed.contains(&placed));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn admins_can_provision_users_while_signup_is_disabled() {
+        let _env = set_env(&[("ALLOW_SIGNUP", Some("false")), ("AUTH_COOKIE_MODE", None)]).await;
+        let mut db = TestDb::new().await;
+        let admin = db.seed_user(ROLE_ADMIN).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let email = format!("{}@example.com", Uuid::new_v4());
+
+        let req = authed(TestRequest::post().uri("/v1/admin/users"), &token_for(admin, ROLE_ADMIN))
+            .set_json(json!({ "email": email, "password": "password123" }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let created: Value = test::read_body_json(resp).await;
+        db.track_user(created["userId"].as_str().unwrap().parse().unwrap());
+        assert_eq!(created["role"], ROLE_MEMBER);
+
+        // The provisioned user can log in even though they could not have signed up
+        let req = TestRequest::post()
+            .uri("/v1/auth")
+            .set_json(json!({ "email": email, "password": "password123", "action": "login" }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -1,5 +1,7 @@
-use actix_web::{web, HttpResponse};
-use chrono::Utc;
+use actix_web::{web, HttpRequest, HttpResponse, HttpResponseBuilder};
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::StatusCode;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -9,7 +11,11 @@ use jsonwebtoken::{encode, Header, EncodingKey};
 use validator::{Validate, ValidationErrors};
 use std::env;
 use rand;
+use log::error;
+use crate::config;
+use crate::errors;
 use crate::utils;
+use crate::models::user::ROLE_MEMBER;
 
 #[derive(Deserialize, Validate)]
 pub struct AuthRequest {
@@ -24,9 +30,33 @@ pub struct AuthRequest {
 #[derive(Serialize)]
 pub struct AuthResponse {
     email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    /// The token's `exp`, so clients know when to log in again
+    #[serde(rename = "expiresAt")]
+    expires_at: DateTime<Utc>,
+    #[serde(rename = "expiresIn")]
+    expires_in: i64,
+    /// Set after an admin reset with `forceChange`, so clients can prompt for a new password
+    #[serde(rename = "mustChangePassword", skip_serializing_if = "std::ops::Not::not")]
+    must_change_password: bool,
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectRequest {
     token: String,
 }
 
+/// Loosely modeled on RFC 7662 token introspection responses
+#[derive(Serialize)]
+pub struct IntrospectResponse {
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<usize>,
+}
+
 fn validate_action(action: &str) -> Result<(), validator::ValidationError> {
     if action != "create" && action != "login" {
         return Err(validator::ValidationError::new("Invalid action"));
@@ -34,37 +64,129 @@ fn validate_action(action: &str) -> Result<(), validator::ValidationError> {
     Ok(())
 }
 
+/// Appends the optional server-side `PASSWORD_PEPPER` to the password bytes.
+/// When unset the password is used as-is, so existing hashes keep verifying.
+pub fn peppered_password(password: &str) -> Vec<u8> {
+    let mut bytes = password.as_bytes().to_vec();
+    if let Ok(pepper) = env::var("PASSWORD_PEPPER") {
+        bytes.extend_from_slice(pepper.as_bytes());
+    }
+    bytes
+}
+
+/// Keeps the first two characters of the local part, e.g. `jo***@example.com`,
+/// so repeated attempts against one account can be spotted without storing the address.
+fn mask_email(email: &str) -> String {
+    let (local, domain) = email.rsplit_once('@').unwrap_or((email, ""));
+    let visible = local.chars().take(2).collect::<String>();
+    if domain.is_empty() {
+        format!("{}***", visible)
+    } else {
+        format!("{}***@{}", visible, domain.to_lowercase())
+    }
+}
+
+/// Public signup is on unless `ALLOW_SIGNUP` is set to something other than a true value
+fn signup_allowed() -> bool {
+    env::var("ALLOW_SIGNUP").is_err() || config::env_flag("ALLOW_SIGNUP")
+}
+
+/// Records a failed login in the background so a slow or failing insert never delays
+/// or changes the auth response.
+fn record_failed_login(pool: &web::Data<PgPool>, http_req: &HttpRequest, email: &str) {
+    let pool = pool.clone();
+    let email = mask_email(email);
+    let source_ip = utils::client_ip::client_ip(http_req);
+
+    actix_web::rt::spawn(async move {
+        if let Err(err) = sqlx::query!(
+            "INSERT INTO failed_logins (email, source_ip, attempted_at) VALUES ($1, $2, $3)",
+            email,
+            source_ip,
+            Utc::now()
+        )
+        .execute(&**pool)
+        .await
+        {
+            error!("Failed to record failed login: {:?}", err);
+        }
+    });
+}
+
+/// Returns the token in the body by default. With `AUTH_COOKIE_MODE` the token is set in
+/// an HttpOnly cookie instead, so browser clients never have it in script-readable storage.
+/// The cookie expires with the token, so the browser never keeps sending a dead one.
+fn auth_response(
+    mut builder: HttpResponseBuilder,
+    email: String,
+    token: String,
+    exp: usize,
+    must_change_password: bool,
+) -> HttpResponse {
+    let expires_at = DateTime::from_timestamp(exp as i64, 0).unwrap_or_default();
+    let expires_in = (expires_at - Utc::now()).num_seconds().max(0);
+
+    let Some(cookie_name) = config::env_flag("AUTH_COOKIE_MODE")
+        .then(utils::jwt::auth_cookie_name)
+        .flatten()
+    else {
+        return builder.json(AuthResponse { email, token: Some(token), expires_at, expires_in, must_change_password });
+    };
+
+    let cookie = Cookie::build(cookie_name, token)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Strict)
+        .max_age(Duration::seconds(expires_in))
+        .finish();
+
+    builder.cookie(cookie).json(AuthResponse { email, token: None, expires_at, expires_in, must_change_password })
+}
+
+fn duplicate_email_error() -> actix_web::Error {
+    utils::response::field_error(StatusCode::CONFLICT, "email", "already taken", "Email already exists")
+}
+
 fn map_sqlx_error(err: sqlx::Error) -> actix_web::Error {
     match err {
         sqlx::Error::RowNotFound => actix_web::error::ErrorNotFound("Resource not found"),
-        _ => actix_web::error::InternalError::new(err, actix_web::http::StatusCode::INTERNAL_SERVER_ERROR).into(),
+        _ => errors::internal_error(err),
     }
 }
 
-fn map_validation_error(err: ValidationErrors) -> actix_web::Error {
-    actix_web::error::ErrorBadRequest(err.to_string())
+fn map_validation_error(req: &HttpRequest, err: ValidationErrors) -> actix_web::Error {
+    let locale = utils::i18n::Locale::from_request(req);
+    actix_web::error::ErrorBadRequest(utils::i18n::format_validation_errors(&err, locale))
 }
 
 pub async fn auth_handler(
+    http_req: HttpRequest,
     req: web::Json<AuthRequest>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    req.0.validate().map_err(map_validation_error)?;
+    req.0.validate().map_err(|err| map_validation_error(&http_req, err))?;
+    utils::validation::validate_strict_email(&req.0.email)?;
 
     match req.action.to_lowercase().as_str() {
         "create" => {
+            // Invite-only deployments provision users through `POST /v1/admin/users`
+            if !signup_allowed() {
+                return Err(actix_web::error::ErrorForbidden("Signup disabled"));
+            }
+
             if sqlx::query!("SELECT email FROM users WHERE LOWER(email) = LOWER($1)", &req.0.email)
                 .fetch_optional(&**pool)
                 .await
                 .map_err(map_sqlx_error)?
                 .is_some()
             {
-                return Err(actix_web::error::ErrorConflict("Email already exists"));
+                return Err(duplicate_email_error());
             }
 
             let salt = SaltString::generate(&mut rand::thread_rng());
             let argon2 = Argon2::default();
-            let password_hash = argon2.hash_password(req.0.password.as_bytes(), &salt)
+            let password_hash = argon2.hash_password(&peppered_password(&req.0.password), &salt)
                 .map_err(|_| actix_web::error::ErrorInternalServerError("Hashing error"))?
                 .to_string();
 
@@ -79,39 +201,282 @@ pub async fn auth_handler(
             let claims = utils::jwt::Claims {
                 sub: user_id.to_string(), // Use user_id instead of email
                 exp: (OffsetDateTime::now_utc() + Duration::days(7)).unix_timestamp() as usize,
+                role: ROLE_MEMBER.to_string(),
             };
             let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(env::var("JWT_SECRET").unwrap().as_ref()))
                 .map_err(|_| actix_web::error::ErrorInternalServerError("Token generation error"))?;
 
-            Ok(HttpResponse::Created().json(AuthResponse {
-                email: req.0.email.clone(),
-                token,
-            }))
+            Ok(auth_response(HttpResponse::Created(), req.0.email.clone(), token, claims.exp, false))
         },
         "login" => {
             let user = sqlx::query!("SELECT * FROM users WHERE LOWER(email) = LOWER($1)", &req.0.email)
-                .fetch_one(&**pool)
+                .fetch_optional(&**pool)
                 .await
                 .map_err(map_sqlx_error)?;
 
+            let Some(user) = user else {
+                record_failed_login(&pool, &http_req, &req.0.email);
+                return Err(actix_web::error::ErrorNotFound("Resource not found"));
+            };
+
             let parsed_hash = argon2::PasswordHash::new(&user.password)
                 .map_err(|_| actix_web::error::ErrorInternalServerError("Invalid password hash"))?;
-            Argon2::default()
-                .verify_password(req.0.password.as_bytes(), &parsed_hash)
-                .map_err(|_| actix_web::error::ErrorUnauthorized("Invalid password"))?;
+            if Argon2::default()
+                .verify_password(&peppered_password(&req.0.password), &parsed_hash)
+                .is_err()
+            {
+                record_failed_login(&pool, &http_req, &req.0.email);
+                return Err(actix_web::error::ErrorUnauthorized("Invalid password"));
+            }
 
             let claims = utils::jwt::Claims {
                 sub: user.user_id.to_string(), // Use user_id instead of email
                 exp: (OffsetDateTime::now_utc() + Duration::days(7)).unix_timestamp() as usize,
+                role: user.role.clone(),
             };
             let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(env::var("JWT_SECRET").unwrap().as_ref()))
                 .map_err(|_| actix_web::error::ErrorInternalServerError("Token generation error"))?;
 
-            Ok(HttpResponse::Ok().json(AuthResponse {
-                email: user.email.clone(),
-                token,
-            }))
+            Ok(auth_response(HttpResponse::Ok(), user.email.clone(), token, claims.exp, user.must_change_password))
         },
         _ => Err(actix_web::error::ErrorBadRequest("Invalid action"))?,
     }
-}
\ No newline at end of file
+}
+
+/// Reports whether a token is valid without requiring it in the `Authorization` header.
+/// Malformed bodies and invalid or expired tokens yield `{ "active": false }` instead of an error.
+pub async fn introspect_handler(
+    req: Option<web::Json<IntrospectRequest>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = req.and_then(|req| utils::jwt::validate_token(&req.token).ok());
+
+    let response = match claims {
+        Some(claims) => IntrospectResponse {
+            active: true,
+            sub: Some(claims.sub),
+            exp: Some(claims.exp),
+        },
+        None => IntrospectResponse {
+            active: false,
+            sub: None,
+            exp: None,
+        },
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body;
+    use actix_web::test;
+    use serde_json::json;
+    use crate::test_support::{init_env, set_env, set_env_blocking, test_app, test_pool, token_for, TestDb};
+
+    #[actix_web::test]
+    async fn cookie_mode_sets_the_token_in_a_cookie_that_expires_with_it() {
+        let _env = set_env(&[("AUTH_COOKIE_MODE", Some("true")), ("AUTH_COOKIE_NAME", None)]).await;
+        let exp = (OffsetDateTime::now_utc() + Duration::hours(1)).unix_timestamp() as usize;
+
+        let resp = auth_response(HttpResponse::Ok(), "jo@example.com".to_string(), "signed-token".to_string(), exp, false);
+
+        let cookie = resp.cookies().next().expect("Set-Cookie header");
+        assert_eq!(cookie.name(), utils::jwt::DEFAULT_AUTH_COOKIE_NAME);
+        assert_eq!(cookie.value(), "signed-token");
+        assert_eq!(cookie.http_only(), Some(true));
+        let max_age = cookie.max_age().expect("Max-Age").whole_seconds();
+        assert!((3590..=3600).contains(&max_age), "Max-Age {}", max_age);
+
+        let body: serde_json::Value = serde_json::from_slice(&body::to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert!(body.get("token").is_none(), "token leaked into {}", body);
+        assert_eq!(body["expiresIn"].as_i64(), Some(max_age));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn failed_login_is_recorded_with_a_masked_email_and_the_peer_ip() {
+        let db = TestDb::new().await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        // A unique domain, since only the domain survives masking intact
+        let domain = format!("{}.example.com", Uuid::new_v4().simple());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth")
+            .peer_addr("203.0.113.7:40000".parse().unwrap())
+            .set_json(json!({ "email": format!("nobody@{}", domain), "password": "password123", "action": "login" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // The row is written in the background after the response
+        let masked = format!("no***@{}", domain);
+        let mut recorded = None;
+        for _ in 0..50 {
+            recorded = sqlx::query_scalar!("SELECT source_ip FROM failed_logins WHERE email = $1", masked)
+                .fetch_optional(&db.pool)
+                .await
+                .unwrap();
+            if recorded.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        sqlx::query!("DELETE FROM failed_logins WHERE email = $1", masked).execute(&db.pool).await.unwrap();
+
+        assert_eq!(recorded, Some(Some("203.0.113.7".to_string())));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn signup_reports_the_expiry_encoded_in_the_token() {
+        let _env = set_env(&[("ALLOW_SIGNUP", None), ("AUTH_COOKIE_MODE", None)]).await;
+        let mut db = TestDb::new().await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let email = format!("{}@example.com", Uuid::new_v4());
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth")
+            .set_json(json!({ "email": email, "password": "password123", "action": "create" }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let user_id = sqlx::query_scalar!("SELECT user_id FROM users WHERE email = $1", email)
+            .fetch_one(&db.pool)
+            .await
+            .expect("signup created the user");
+        db.track_user(user_id);
+
+        let claims = utils::jwt::validate_token(body["token"].as_str().unwrap()).unwrap();
+        let expires_at: DateTime<Utc> = body["expiresAt"].as_str().unwrap().parse().unwrap();
+        assert_eq!(expires_at.timestamp(), claims.exp as i64);
+
+        // Computed when the response was built, so at most a few seconds short of exp
+        let until_exp = claims.exp as i64 - Utc::now().timestamp();
+        let expires_in = body["expiresIn"].as_i64().unwrap();
+        assert!((until_exp..=until_exp + 5).contains(&expires_in), "expiresIn {} vs {}", expires_in, until_exp);
+    }
+
+    async fn introspect(body: serde_json::Value) -> serde_json::Value {
+        let app = test::init_service(test_app(test_pool())).await;
+        let req = test::TestRequest::post().uri("/v1/auth/introspect").set_json(body).to_request();
+        test::call_and_read_body_json(&app, req).await
+    }
+
+    #[actix_web::test]
+    async fn introspect_reports_a_valid_token_as_active() {
+        let user_id = Uuid::new_v4();
+        let token = token_for(user_id, ROLE_MEMBER);
+        let exp = utils::jwt::validate_token(&token).unwrap().exp;
+
+        let body = introspect(json!({ "token": token })).await;
+
+        assert_eq!(body, json!({ "active": true, "sub": user_id.to_string(), "exp": exp }));
+    }
+
+    #[actix_web::test]
+    async fn introspect_reports_bad_tokens_as_inactive() {
+        init_env();
+        let claims = |exp: OffsetDateTime| utils::jwt::Claims {
+            sub: Uuid::new_v4().to_string(),
+            exp: exp.unix_timestamp() as usize,
+            role: ROLE_MEMBER.to_string(),
+        };
+        let expired = claims(OffsetDateTime::now_utc() - Duration::hours(1));
+        let expired = encode(&Header::default(), &expired, &EncodingKey::from_secret(b"test-secret")).unwrap();
+        let current = claims(OffsetDateTime::now_utc() + Duration::hours(1));
+        let foreign = encode(&Header::default(), &current, &EncodingKey::from_secret(b"another-secret")).unwrap();
+
+        for body in [json!({ "token": expired }), json!({ "token": foreign }), json!({ "token": "not-a-jwt" }), json!({})] {
+            assert_eq!(introspect(body.clone()).await, json!({ "active": false }), "{}", body);
+        }
+    }
+
+    fn hash(password: &[u8]) -> String {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        Argon2::default().hash_password(password, &salt).unwrap().to_string()
+    }
+
+    fn verifies(hash: &str, password: &str) -> bool {
+        let hash = argon2::PasswordHash::new(hash).unwrap();
+        Argon2::default().verify_password(&peppered_password(password), &hash).is_ok()
+    }
+
+    fn with_pepper<T>(pepper: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let _env = set_env_blocking(&[("PASSWORD_PEPPER", pepper)]);
+        f()
+    }
+
+    #[test]
+    fn passwords_verify_without_a_pepper() {
+        with_pepper(None, || {
+            let stored = hash(&peppered_password("correct horse"));
+
+            assert!(verifies(&stored, "correct horse"));
+            assert!(!verifies(&stored, "wrong horse"));
+            // Hashes made before peppering existed are plain argon2 of the password
+            assert!(verifies(&hash(b"correct horse"), "correct horse"));
+        });
+    }
+
+    #[test]
+    fn passwords_only_verify_with_the_pepper_they_were_hashed_with() {
+        let stored = with_pepper(Some("pepper-one"), || hash(&peppered_password("correct horse")));
+
+        assert!(with_pepper(Some("pepper-one"), || verifies(&stored, "correct horse")));
+        assert!(!with_pepper(Some("pepper-one"), || verifies(&stored, "wrong horse")));
+        assert!(!with_pepper(Some("pepper-two"), || verifies(&stored, "correct horse")));
+        assert!(!with_pepper(None, || verifies(&stored, "correct horse")));
+    }
+
+    #[actix_web::test]
+    async fn signup_is_refused_when_disabled() {
+        let _env = set_env(&[("ALLOW_SIGNUP", Some("false"))]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth")
+            .set_json(json!({ "email": "new@example.com", "password": "password123", "action": "create" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(test::read_body(resp).await, "Signup disabled");
+    }
+
+    async fn duplicate_email_body(structured: Option<&str>) -> (StatusCode, Vec<u8>) {
+        let _env = set_env(&[("STRUCTURED_FIELD_ERRORS", structured)]).await;
+        let resp = duplicate_email_error().error_response();
+        (resp.status(), body::to_bytes(resp.into_body()).await.unwrap().to_vec())
+    }
+
+    #[actix_web::test]
+    async fn duplicate_email_is_a_field_error_in_structured_mode() {
+        let (status, body) = duplicate_email_body(Some("true")).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!({ "fields": { "email": ["already taken"] } }));
+
+        let (status, body) = duplicate_email_body(None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body, b"Email already exists");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn signup_with_a_taken_email_reports_the_field() {
+        let _env = set_env(&[("ALLOW_SIGNUP", None), ("STRUCTURED_FIELD_ERRORS", Some("true"))]).await;
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = test::TestRequest::post()
+            .uri("/v1/auth")
+            .set_json(json!({ "email": format!("{}@example.com", user_id), "password": "password123", "action": "create" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body, json!({ "fields": { "email": ["already taken"] } }));
+    }
+}
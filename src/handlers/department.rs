@@ -6,11 +6,24 @@ use uuid::Uuid;
 use chrono::Utc;
 use jsonwebtoken::errors::Error as JwtError;
 use validator::ValidationErrors;
+use crate::config::{Config, ListOrder};
+use crate::db;
 use crate::utils;
-use crate::models::department::Department;
+use crate::utils::jwt::Claims;
+use crate::models::department::{Department, STATUS_ACTIVE, STATUS_ARCHIVED};
+use crate::models::pagination::Page;
+use crate::models::employee::Employee;
+use std::collections::HashMap;
+use sqlx::{Postgres, QueryBuilder};
 use crate::errors::AppError;
 use actix_web::error::{ErrorBadRequest, ErrorUnauthorized, ErrorConflict, ErrorNotFound};
 
+/// Retries after losing a race for a department code
+const CODE_INSERT_ATTEMPTS: u32 = 3;
+
+/// Unique index on the normalized name: case-folded, trimmed, inner whitespace collapsed
+const NAME_UNIQUE_INDEX: &str = "idx_departments_name_normalized";
+
 #[derive(Deserialize, Validate)]
 pub struct NewDepartment {
     #[validate(length(min = 4, max = 33))]
@@ -22,61 +35,114 @@ struct DepartmentResponse {
     #[serde(rename = "departmentId")]
     department_id: Uuid,
     name: String,
+    code: String,
 }
 
 #[derive(Deserialize)]
 pub struct DepartmentQueryParams {
     name: Option<String>,
+    include_archived: Option<bool>,
     limit: Option<i64>,
     offset: Option<i64>,
+    envelope: Option<bool>,
+    fields: Option<String>,
 }
 
+/// Fields clients may select from department responses with `fields`
+const DEPARTMENT_FIELDS: &[&str] = &["departmentId", "name", "code", "status", "createdAt", "updatedAt"];
+
 #[derive(Deserialize, Validate)]
 pub struct DepartmentUpdate {
     #[validate(length(min = 4, max = 33))]
     name: String,
 }
 
-fn map_validation_error(err: ValidationErrors) -> actix_web::Error {
-    ErrorBadRequest(json!({ "error": err.to_string() }))
+#[derive(Deserialize)]
+pub struct ReassignRequest {
+    #[serde(alias = "targetDepartmentId")]
+    target_department_id: String,
+}
+
+fn map_validation_error(req: &HttpRequest, err: ValidationErrors) -> actix_web::Error {
+    let locale = utils::i18n::Locale::from_request(req);
+    ErrorBadRequest(json!({ "error": utils::i18n::format_validation_errors(&err, locale) }))
 }
 
 fn map_jwt_error(_err: JwtError) -> actix_web::Error {
-    ErrorUnauthorized(json!({ "error": "Invalid or expired token" }))
+    ErrorUnauthorized(json!({ "error": utils::jwt::INVALID_TOKEN_MESSAGE }))
 }
 
 /// Extracts and validates the token from the request.
 /// Returns `401 Unauthorized` if the token is missing or empty.
-fn extract_and_validate_token(req: &HttpRequest) -> Result<(), actix_web::Error> {
-    let token = req.headers()
-        .get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1))
-        .filter(|token| !token.is_empty())
-        .ok_or_else(|| ErrorUnauthorized(json!({ "error": "Missing or empty token" })))?;
+fn extract_and_validate_token(req: &HttpRequest) -> Result<Claims, actix_web::Error> {
+    let token = utils::jwt::extract_token(req)
+        .ok_or_else(|| ErrorUnauthorized(json!({ "error": utils::jwt::MISSING_TOKEN_MESSAGE })))?;
 
     // Validate the token
-    utils::jwt::validate_token(token).map_err(map_jwt_error)?;
-    Ok(())
+    utils::jwt::validate_token(&token).map_err(map_jwt_error)
+}
+
+/// True when the unique index on department names rejected a write.
+fn is_duplicate_name(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(db_err)
+        if db_err.code().as_deref() == Some("23505") && db_err.constraint() == Some(NAME_UNIQUE_INDEX))
+}
+
+/// Maps a department to its camelCase JSON representation.
+fn department_json(dept: &Department) -> serde_json::Value {
+    json!({
+        "departmentId": dept.department_id,
+        "name": dept.name,
+        "code": dept.code,
+        "status": dept.status,
+        "createdAt": utils::date_format::to_value(&dept.created_at),
+        "updatedAt": utils::date_format::to_value(&dept.updated_at),
+    })
+}
+
+/// Applies the listing filters shared by the page and count queries.
+fn push_department_filters(query_builder: &mut QueryBuilder<'_, Postgres>, query: &DepartmentQueryParams) {
+    let mut has_conditions = false;
+
+    if let Some(name) = &query.name {
+        query_builder.push(" WHERE name ILIKE ");
+        query_builder.push_bind(format!("%{}%", name));
+        has_conditions = true;
+    }
+
+    // Archived departments are hidden unless explicitly requested
+    if !query.include_archived.unwrap_or(false) {
+        query_builder.push(if has_conditions { " AND " } else { " WHERE " });
+        query_builder.push("status = ");
+        query_builder.push_bind(STATUS_ACTIVE);
+    }
 }
 
 pub async fn create_department(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
-    new_department: web::Json<NewDepartment>,
+    mut new_department: web::Json<NewDepartment>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Validate the token (FIRST STEP)
     extract_and_validate_token(&req)?;
 
-    // Validate the input payload (SECOND STEP)
-    new_department.validate().map_err(map_validation_error)?;
-
-    // Check if the department name already exists
-    if sqlx::query!("SELECT name FROM departments WHERE name = $1", &new_department.name)
-        .fetch_optional(&**pool)
-        .await
-        .map_err(|err| AppError::DatabaseError(err.to_string()))?
-        .is_some()
+    // Validate the input payload (SECOND STEP); names are trimmed first
+    new_department.name = utils::text::normalize_name(&new_department.name);
+    new_department.validate().map_err(|err| map_validation_error(&req, err))?;
+
+    // Fast path for the common case; the unique index on the normalized name is the real
+    // guarantee. Case and extra whitespace are ignored, so "HR " collides with "hr".
+    if sqlx::query!(
+        r#"
+        SELECT name FROM departments
+        WHERE LOWER(REGEXP_REPLACE(TRIM(name), '\s+', ' ', 'g')) = LOWER(REGEXP_REPLACE(TRIM($1), '\s+', ' ', 'g'))
+        "#,
+        &new_department.name
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?
+    .is_some()
     {
         return Err(ErrorConflict(json!({ "error": "Department name already exists" })));
     }
@@ -84,43 +150,72 @@ pub async fn create_department(
     // Generate a new department ID and current timestamp
     let department_id = Uuid::new_v4();
     let now = Utc::now();
+    let base_code = utils::slug::slugify(&new_department.name);
+
+    // Another request may claim the same code between the lookup and the insert,
+    // in which case the unique index rejects it and we pick the next free suffix
+    let mut attempts = 0;
+    let code = loop {
+        let taken = sqlx::query_scalar!(
+            "SELECT code FROM departments WHERE code = $1 OR code LIKE $2",
+            base_code,
+            format!("{}-%", base_code)
+        )
+        .fetch_all(&**pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?;
 
-    // Insert the new department into the database
-    sqlx::query!(
-        "INSERT INTO departments (department_id, name, created_at, updated_at) VALUES ($1, $2, $3, $4)",
-        department_id,
-        &new_department.name,
-        now,
-        now
-    )
-    .execute(&**pool)
-    .await
-    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+        let code = utils::slug::unique_slug(&base_code, &taken);
+
+        // Insert the new department into the database
+        let inserted = sqlx::query!(
+            "INSERT INTO departments (department_id, name, code, created_at, updated_at) VALUES ($1, $2, $3, $4, $5)",
+            department_id,
+            &new_department.name,
+            code,
+            now,
+            now
+        )
+        .execute(&**pool)
+        .await;
+
+        match inserted {
+            Ok(_) => break code,
+            // A concurrent request created the same name after our pre-check
+            Err(err) if is_duplicate_name(&err) => {
+                return Err(ErrorConflict(json!({ "error": "Department name already exists" })));
+            }
+            Err(sqlx::Error::Database(err)) if err.code().as_deref() == Some("23505") && attempts < CODE_INSERT_ATTEMPTS => {
+                attempts += 1;
+            }
+            Err(err) => return Err(AppError::DatabaseError(err.to_string()).into()),
+        }
+    };
 
     // Return the created department as a response
     Ok(HttpResponse::Created().json(DepartmentResponse {
         department_id,
         name: new_department.name.clone(),
+        code,
     }))
 }
 
 pub async fn get_departments(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
+    app_config: web::Data<Config>,
     query: web::Query<DepartmentQueryParams>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Validate the token (FIRST STEP)
     extract_and_validate_token(&req)?;
 
-    // Build the SQL query dynamically based on query parameters
-    let mut query_builder = sqlx::QueryBuilder::new("SELECT * FROM departments");
+    let fields = utils::fields::FieldSelection::parse(query.fields.as_deref(), DEPARTMENT_FIELDS)?;
 
-    if let Some(name) = &query.name {
-        query_builder.push(" WHERE name ILIKE ");
-        query_builder.push_bind(format!("%{}%", name));
-    }
+    // Build the SQL query dynamically based on query parameters
+    let mut query_builder = QueryBuilder::new("SELECT * FROM departments");
+    push_department_filters(&mut query_builder, &query);
 
-    query_builder.push(" ORDER BY created_at DESC");
+    query_builder.push(app_config.default_order.order_by("department_id"));
 
     if let Some(limit) = query.limit {
         query_builder.push(" LIMIT ");
@@ -140,28 +235,176 @@ pub async fn get_departments(
         .map_err(|err| AppError::DatabaseError(err.to_string()))?;
 
     // Map the response to camelCase keys
+    let response = departments
+        .iter()
+        .map(|dept| fields.apply(&department_json(dept)))
+        .collect::<Vec<_>>();
+
+    // Wrap the listing in `{ data, meta }` only when the client opts in
+    if query.envelope.unwrap_or(false) {
+        let mut count_builder = QueryBuilder::new("SELECT COUNT(*) FROM departments");
+        push_department_filters(&mut count_builder, &query);
+
+        let total: i64 = count_builder
+            .build_query_scalar()
+            .fetch_one(&**pool)
+            .await
+            .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+        return Ok(HttpResponse::Ok().json(Page::new(response, query.limit, query.offset, total)));
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn get_departments_with_employees(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    app_config: web::Data<Config>,
+    query: web::Query<DepartmentQueryParams>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    let claims = extract_and_validate_token(&req)?;
+
+    // Fetch the requested page of departments
+    let mut query_builder = QueryBuilder::new("SELECT * FROM departments");
+    push_department_filters(&mut query_builder, &query);
+    query_builder.push(app_config.default_order.order_by("department_id"));
+
+    if let Some(limit) = query.limit {
+        query_builder.push(" LIMIT ");
+        query_builder.push_bind(limit);
+    }
+
+    if let Some(offset) = query.offset {
+        query_builder.push(" OFFSET ");
+        query_builder.push_bind(offset);
+    }
+
+    let departments = query_builder
+        .build_query_as::<Department>()
+        .fetch_all(&**pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    // Fetch all employees of those departments in one query instead of one per department
+    let department_ids = departments.iter().map(|dept| dept.department_id).collect::<Vec<_>>();
+    let mut employees = sqlx::query_as!(
+        Employee,
+        "SELECT * FROM employees WHERE department_id = ANY($1) ORDER BY created_at DESC, employee_id DESC",
+        &department_ids
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    if app_config.default_order == ListOrder::NameAsc {
+        employees.sort_by(|a, b| a.name.cmp(&b.name).then(a.employee_id.cmp(&b.employee_id)));
+    }
+
+    let mut employees_by_department: HashMap<Uuid, Vec<Employee>> = HashMap::new();
+    for employee in employees {
+        employees_by_department
+            .entry(employee.department_id)
+            .or_default()
+            .push(employee.redact_salary(claims.is_admin()));
+    }
+
     let response = departments.into_iter().map(|dept| json!({
         "departmentId": dept.department_id,
         "name": dept.name,
-        "createdAt": dept.created_at,
-        "updatedAt": dept.updated_at,
+        "code": dept.code,
+        "employees": employees_by_department.remove(&dept.department_id).unwrap_or_default(),
     }))
     .collect::<Vec<_>>();
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Counts the departments matching the listing filters without fetching them.
+pub async fn count_departments(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<DepartmentQueryParams>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    extract_and_validate_token(&req)?;
+
+    let mut count_builder = QueryBuilder::new("SELECT COUNT(*) FROM departments");
+    push_department_filters(&mut count_builder, &query);
+
+    let count: i64 = count_builder
+        .build_query_scalar()
+        .fetch_one(&**pool)
+        .await
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(json!({ "count": count })))
+}
+
+pub async fn get_department_by_name(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    name: web::Path<String>,
+    query: web::Query<utils::fields::FieldsQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    extract_and_validate_token(&req)?;
+
+    let fields = utils::fields::FieldSelection::parse(query.fields.as_deref(), DEPARTMENT_FIELDS)?;
+
+    // Path segments arrive percent-decoded, so names with spaces match as-is
+    let name = name.into_inner();
+
+    let department = sqlx::query_as!(
+        Department,
+        "SELECT * FROM departments WHERE LOWER(name) = LOWER($1) ORDER BY created_at, department_id LIMIT 1",
+        name
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?
+    .ok_or_else(|| ErrorNotFound(json!({ "error": "Department not found" })))?;
+
+    Ok(HttpResponse::Ok().json(fields.apply(&department_json(&department))))
+}
+
+pub async fn get_department_by_code(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    code: web::Path<String>,
+    query: web::Query<utils::fields::FieldsQuery>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    extract_and_validate_token(&req)?;
+
+    let fields = utils::fields::FieldSelection::parse(query.fields.as_deref(), DEPARTMENT_FIELDS)?;
+
+    let department = sqlx::query_as!(
+        Department,
+        "SELECT * FROM departments WHERE code = $1",
+        code.into_inner().to_lowercase()
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?
+    .ok_or_else(|| ErrorNotFound(json!({ "error": "Department not found" })))?;
+
+    Ok(HttpResponse::Ok().json(fields.apply(&department_json(&department))))
+}
+
 pub async fn update_department(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
     department_id: web::Path<String>,
-    updates: web::Json<DepartmentUpdate>,
+    mut updates: web::Json<DepartmentUpdate>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Validate the token (FIRST STEP)
     extract_and_validate_token(&req)?;
 
-    // Validate the input payload (SECOND STEP)
-    updates.validate().map_err(map_validation_error)?;
+    // Validate the input payload (SECOND STEP); names are trimmed first
+    updates.name = utils::text::normalize_name(&updates.name);
+    updates.validate().map_err(|err| map_validation_error(&req, err))?;
 
     // Parse the department ID
     let department_id = Uuid::parse_str(&department_id.into_inner())
@@ -171,10 +414,15 @@ pub async fn update_department(
     let department = sqlx::query!("SELECT * FROM departments WHERE department_id = $1", department_id)
         .fetch_optional(&**pool)
         .await
-        .map_err(|err| AppError::DatabaseError(err.to_string()))?;
-
-    if department.is_none() {
-        return Err(ErrorNotFound(json!({ "error": "Department not found" })));
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?
+        .ok_or_else(|| ErrorNotFound(json!({ "error": "Department not found" })))?;
+
+    // Renaming to the current name is a no-op; skip the write so `updated_at` stays put
+    if department.name == updates.name {
+        return Ok(HttpResponse::Ok().json(json!({
+            "departmentId": department_id,
+            "name": department.name,
+        })));
     }
 
     // Update the department
@@ -187,7 +435,13 @@ pub async fn update_department(
     )
     .execute(&**pool)
     .await
-    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+    .map_err(|err| {
+        if is_duplicate_name(&err) {
+            ErrorConflict(json!({ "error": "Department name already exists" }))
+        } else {
+            AppError::DatabaseError(err.to_string()).into()
+        }
+    })?;
 
     // Return the updated department
     Ok(HttpResponse::Ok().json(json!({
@@ -234,7 +488,449 @@ pub async fn delete_department(
         .await
         .map_err(|err| AppError::DatabaseError(err.to_string()))?;
 
+    Ok(utils::response::deleted("Department deleted successfully"))
+}
+
+/// Moves every employee of a department to another one and deletes the emptied
+/// department, all in one transaction.
+pub async fn delete_department_with_reassign(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    department_id: web::Path<String>,
+    body: web::Json<ReassignRequest>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    extract_and_validate_token(&req)?;
+
+    // Parse both department IDs
+    let department_id = Uuid::parse_str(&department_id.into_inner())
+        .map_err(|_| ErrorBadRequest(json!({ "error": "Invalid department ID" })))?;
+    let target_department_id = Uuid::parse_str(&body.target_department_id)
+        .map_err(|_| ErrorBadRequest(json!({ "error": "Invalid target department ID" })))?;
+
+    if department_id == target_department_id {
+        return Err(ErrorBadRequest(json!({ "error": "Target department must differ from the deleted department" })));
+    }
+
+    let reassigned = db::with_transaction(&pool, move |tx| Box::pin(async move {
+        // Lock both rows so neither is deleted or archived while employees move
+        let departments = sqlx::query!(
+            "SELECT department_id, status FROM departments WHERE department_id = ANY($1) FOR UPDATE",
+            &[department_id, target_department_id][..]
+        )
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+        if !departments.iter().any(|dept| dept.department_id == department_id) {
+            return Err(ErrorNotFound(json!({ "error": "Department not found" })));
+        }
+
+        let target = departments
+            .iter()
+            .find(|dept| dept.department_id == target_department_id)
+            .ok_or_else(|| ErrorNotFound(json!({ "error": "Target department not found" })))?;
+
+        if target.status == STATUS_ARCHIVED {
+            return Err(ErrorConflict(json!({ "error": "Target department is archived" })));
+        }
+
+        let reassigned = sqlx::query!(
+            "UPDATE employees SET department_id = $1, updated_at = $2 WHERE department_id = $3",
+            target_department_id,
+            Utc::now(),
+            department_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|err| AppError::DatabaseError(err.to_string()))?
+        .rows_affected();
+
+        sqlx::query!("DELETE FROM departments WHERE department_id = $1", department_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+        Ok::<_, actix_web::Error>(reassigned)
+    }))
+    .await?;
+
     Ok(HttpResponse::Ok().json(json!({
         "message": "Department deleted successfully",
+        "targetDepartmentId": target_department_id,
+        "reassignedEmployees": reassigned,
+    })))
+}
+
+pub async fn archive_department(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    department_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_department_status(req, pool, department_id, STATUS_ARCHIVED).await
+}
+
+pub async fn unarchive_department(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    department_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_department_status(req, pool, department_id, STATUS_ACTIVE).await
+}
+
+async fn set_department_status(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    department_id: web::Path<String>,
+    status: &str,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Validate the token (FIRST STEP)
+    extract_and_validate_token(&req)?;
+
+    // Parse the department ID
+    let department_id = Uuid::parse_str(&department_id.into_inner())
+        .map_err(|_| ErrorBadRequest(json!({ "error": "Invalid department ID" })))?;
+
+    // Update the status, which also tells us whether the department exists
+    let department = sqlx::query!(
+        "UPDATE departments SET status = $1, updated_at = $2 WHERE department_id = $3 RETURNING name",
+        status,
+        Utc::now(),
+        department_id
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?
+    .ok_or_else(|| ErrorNotFound(json!({ "error": "Department not found" })))?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "departmentId": department_id,
+        "name": department.name,
+        "status": status,
     })))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::dev::ServiceResponse;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test;
+    use futures_util::future::join_all;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::app;
+    use crate::config::ListOrder;
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{authed, state_with, test_app, test_config, test_pool, token_for, TestDb};
+
+    fn get(uri: &str) -> test::TestRequest {
+        authed(test::TestRequest::get().uri(uri), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn finds_a_department_by_a_url_encoded_name() {
+        let mut db = TestDb::new().await;
+        let marker = Uuid::new_v4().simple().to_string();
+        let department_id = db.seed_department(&format!("Sales Ops {}", marker)).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/department/by-name/SALES%20OPS%20{}", marker);
+        let department: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert_eq!(department["departmentId"], json!(department_id));
+
+        let uri = format!("/v1/department/by-name/Sales%20Ops%20{}", Uuid::new_v4().simple());
+        let resp = test::call_service(&app, get(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn counts_departments_matching_a_name_substring() {
+        let mut db = TestDb::new().await;
+        let marker = Uuid::new_v4().simple().to_string();
+        db.seed_department(&format!("Alpha {} Team", marker)).await;
+        db.seed_department(&format!("{} Beta", marker)).await;
+        db.seed_department(&format!("Gamma {}", Uuid::new_v4().simple())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/department/count?name={}", marker.to_uppercase());
+        let count: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+
+        assert_eq!(count, json!({ "count": 2 }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn nests_employees_under_their_department() {
+        let mut db = TestDb::new().await;
+        let marker = Uuid::new_v4().simple().to_string();
+        let first = db.seed_department(&format!("First {}", marker)).await;
+        let second = db.seed_department(&format!("Second {}", marker)).await;
+        let empty = db.seed_department(&format!("Third {}", marker)).await;
+        let first_employees = [
+            db.seed_employee(first, "male", "active").await,
+            db.seed_employee(first, "female", "active").await,
+        ];
+        let second_employee = db.seed_employee(second, "female", "on_leave").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/department/with-employees?name={}", marker);
+        let departments: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+
+        let employee_ids = |department_id: Uuid| {
+            let department = departments
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|department| department["departmentId"] == json!(department_id))
+                .expect("department is listed");
+            let mut ids = department["employees"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|employee| employee["employee_id"].as_str().unwrap().parse::<Uuid>().unwrap())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let mut expected = first_employees.to_vec();
+        expected.sort();
+
+        assert_eq!(departments.as_array().unwrap().len(), 3);
+        assert_eq!(employee_ids(first), expected);
+        assert_eq!(employee_ids(second), vec![second_employee]);
+        assert_eq!(employee_ids(empty), Vec::<Uuid>::new());
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn archived_departments_are_listed_only_on_request() {
+        let mut db = TestDb::new().await;
+        let marker = Uuid::new_v4().simple().to_string();
+        let active = db.seed_department(&format!("Active {}", marker)).await;
+        let archived = db.seed_department(&format!("Archived {}", marker)).await;
+        sqlx::query!("UPDATE departments SET status = 'archived' WHERE department_id = $1", archived)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let listed = |departments: Value| {
+            let mut ids = departments
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|department| department["departmentId"].as_str().unwrap().parse::<Uuid>().unwrap())
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+
+        let uri = format!("/v1/department?name={}", marker);
+        let departments = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert_eq!(listed(departments), vec![active]);
+
+        let uri = format!("/v1/department?name={}&include_archived=true", marker);
+        let departments = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        let mut both = vec![active, archived];
+        both.sort();
+        assert_eq!(listed(departments), both);
+    }
+
+    fn reassign(department_id: Uuid, target_department_id: Uuid) -> test::TestRequest {
+        let uri = format!("/v1/department/{}/delete-with-reassign", department_id);
+        authed(test::TestRequest::post().uri(&uri), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(json!({ "target_department_id": target_department_id }))
+    }
+
+    #[actix_web::test]
+    async fn reassigning_to_the_same_department_is_rejected() {
+        let app = test::init_service(test_app(test_pool())).await;
+        let department_id = Uuid::new_v4();
+
+        let resp = test::call_service(&app, reassign(department_id, department_id).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(body, json!({ "error": "Target department must differ from the deleted department" }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn reassigns_employees_then_deletes_the_department() {
+        let mut db = TestDb::new().await;
+        let source = db.seed_department(&format!("Closing {}", Uuid::new_v4())).await;
+        let target = db.seed_department(&format!("Receiving {}", Uuid::new_v4())).await;
+        let employees = [
+            db.seed_employee(source, "male", "active").await,
+            db.seed_employee(source, "female", "on_leave").await,
+        ];
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let body: Value = test::call_and_read_body_json(&app, reassign(source, target).to_request()).await;
+        assert_eq!(body["reassignedEmployees"], 2);
+        assert_eq!(body["targetDepartmentId"], json!(target));
+
+        let remaining = sqlx::query_scalar!("SELECT COUNT(*) FROM departments WHERE department_id = $1", source)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(remaining, Some(0));
+        let moved = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM employees WHERE employee_id = ANY($1) AND department_id = $2",
+            &employees[..],
+            target
+        )
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+        assert_eq!(moved, Some(2));
+    }
+
+    #[actix_web::test]
+    async fn length_violations_follow_accept_language() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let req = authed(test::TestRequest::post().uri("/v1/department"), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .insert_header((header::ACCEPT_LANGUAGE, "es-ES,es;q=0.9,en;q=0.8"))
+            .set_json(json!({ "name": "HR" }));
+        let resp = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(body, json!({ "error": "name: debe tener entre 4 y 33 caracteres" }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn renaming_to_the_current_name_leaves_updated_at_alone() {
+        let mut db = TestDb::new().await;
+        let name = format!("Same {}", Uuid::new_v4().simple());
+        let department_id = db.seed_department(&name).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let updated_at = || sqlx::query_scalar!("SELECT updated_at FROM departments WHERE department_id = $1", department_id).fetch_one(&db.pool);
+        let before = updated_at().await.unwrap();
+
+        let req = authed(test::TestRequest::patch().uri(&format!("/v1/department/{}", department_id)), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(json!({ "name": name }));
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+
+        assert_eq!(body, json!({ "departmentId": department_id, "name": name }));
+        assert_eq!(updated_at().await.unwrap(), before);
+    }
+
+    fn create(name: &str) -> test::TestRequest {
+        authed(test::TestRequest::post().uri("/v1/department"), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(json!({ "name": name }))
+    }
+
+    #[actix_web::test]
+    async fn a_name_of_only_spaces_fails_length_validation() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let resp = test::call_service(&app, create("          ").to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&test::read_body(resp).await).unwrap();
+        assert_eq!(body, json!({ "error": "name: must be between 4 and 33 characters" }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn padded_names_are_stored_trimmed() {
+        let mut db = TestDb::new().await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let name = format!("Ops {}", &Uuid::new_v4().simple().to_string()[..8]);
+
+        let created: Value = test::call_and_read_body_json(&app, create(&format!("   {}  ", name)).to_request()).await;
+        let department_id: Uuid = created["departmentId"].as_str().unwrap().parse().unwrap();
+        db.track_department(department_id);
+
+        assert_eq!(created["name"], name);
+        let stored = sqlx::query_scalar!("SELECT name FROM departments WHERE department_id = $1", department_id)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, name);
+    }
+
+    /// The status of each response; departments that were created are tracked for cleanup
+    async fn track_created(db: &mut TestDb, responses: Vec<ServiceResponse>) -> Vec<StatusCode> {
+        let mut statuses = Vec::new();
+        for resp in responses {
+            statuses.push(resp.status());
+            if resp.status() == StatusCode::CREATED {
+                let created: Value = test::read_body_json(resp).await;
+                db.track_department(created["departmentId"].as_str().unwrap().parse().unwrap());
+            }
+        }
+        statuses
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn racing_creates_of_one_name_give_a_single_201_and_409s() {
+        let mut db = TestDb::new().await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let name = format!("Race {}", &Uuid::new_v4().simple().to_string()[..8]);
+
+        let responses = join_all((0..8).map(|_| test::call_service(&app, create(&name).to_request()))).await;
+        let statuses = track_created(&mut db, responses).await;
+
+        assert_eq!(statuses.iter().filter(|status| **status == StatusCode::CREATED).count(), 1, "{:?}", statuses);
+        assert!(statuses.iter().all(|status| [StatusCode::CREATED, StatusCode::CONFLICT].contains(status)), "{:?}", statuses);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn names_differing_in_padding_or_case_collide() {
+        let mut db = TestDb::new().await;
+        let marker = &Uuid::new_v4().simple().to_string()[..8];
+        // Stored raw, as rows from before names were trimmed may be
+        db.seed_department(&format!("HR {} ", marker)).await;
+        let other = db.seed_department(&format!("Finance {}", marker)).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let conflict = json!({ "error": "Department name already exists" });
+
+        let resp = test::call_service(&app, create(&format!("hr {}", marker)).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(serde_json::from_slice::<Value>(&test::read_body(resp).await).unwrap(), conflict);
+
+        let req = authed(test::TestRequest::patch().uri(&format!("/v1/department/{}", other)), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(json!({ "name": format!("HR   {}", marker.to_uppercase()) }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(serde_json::from_slice::<Value>(&test::read_body(resp).await).unwrap(), conflict);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn listings_follow_the_configured_default_order() {
+        let mut db = TestDb::new().await;
+        let marker = Uuid::new_v4().simple().to_string();
+        // Inserted newest-last, so the two orders disagree
+        let bravo = db.seed_department(&format!("Bravo {}", marker)).await;
+        let alpha = db.seed_department(&format!("Alpha {}", marker)).await;
+        let charlie = db.seed_department(&format!("Charlie {}", marker)).await;
+
+        for (order, expected) in [
+            (ListOrder::CreatedDesc, [charlie, alpha, bravo]),
+            (ListOrder::NameAsc, [alpha, bravo, charlie]),
+        ] {
+            let mut config = test_config();
+            config.default_order = order;
+            let app = test::init_service(app::build_app(&state_with(config, db.pool.clone()))).await;
+
+            let uri = format!("/v1/department?name={}", marker);
+            let departments: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+            let listed = departments
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|department| department["departmentId"].as_str().unwrap().parse::<Uuid>().unwrap())
+                .collect::<Vec<_>>();
+
+            assert_eq!(listed, expected, "{:?}", order);
+        }
+    }
+}
@@ -1,25 +1,43 @@
 use actix_web::{web, HttpResponse, HttpRequest};
+use actix_web::http::StatusCode;
 use serde::{Deserialize, Serialize};
 // use time::OffsetDateTime;
 use validator::Validate;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Postgres, QueryBuilder};
+use crate::config::{self, Config, ListOrder};
+use crate::errors;
 use crate::utils;
-use crate::models::employee::Employee;
+use crate::models::employee::{Employee, EMPLOYMENT_ACTIVE, EMPLOYMENT_STATUSES};
+use crate::models::department::STATUS_ARCHIVED;
+use crate::models::pagination::Page;
 use serde_json::json;
+use std::collections::HashMap;
 
+/// Fields are snake_case; each also accepts its camelCase name.
 #[derive(Deserialize, Validate)]
 pub struct NewEmployee {
     #[validate(length(min = 5, max = 33))]
+    #[serde(alias = "identityNumber")]
     identity_number: String,
     #[validate(length(min = 4, max = 33))]
     name: String,
     #[validate(url)]
+    #[serde(alias = "employeeImageUri")]
     employee_image_uri: Option<String>,
     #[validate(custom = "validate_gender")]
     gender: String,
     #[validate(length(min = 36, max = 36))]
+    #[serde(alias = "departmentId")]
     department_id: String,
+    #[validate(custom = "validate_employment_status")]
+    #[serde(alias = "employmentStatus")]
+    employment_status: Option<String>,
+    #[serde(alias = "hireDate")]
+    hire_date: Option<NaiveDate>,
+    #[validate(range(min = 0))]
+    salary: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -29,6 +47,10 @@ struct EmployeeResponse {
     employee_image_uri: Option<String>,
     gender: String,
     department_id: String,
+    employment_status: String,
+    hire_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    salary: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -37,22 +59,206 @@ pub struct EmployeeQueryParams {
     name: Option<String>,
     gender: Option<String>,
     department_id: Option<String>,
+    employment_status: Option<String>,
     limit: Option<i64>,
     offset: Option<i64>,
+    cursor: Option<String>,
+    envelope: Option<bool>,
+    fields: Option<String>,
+    expand: Option<String>,
 }
 
+/// Relations that `expand` can inline into employee listings
+const EXPANDABLE: &[&str] = &["department"];
+
+/// Fields clients may select from employee listings with `fields`
+const EMPLOYEE_FIELDS: &[&str] = &[
+    "employee_id",
+    "identity_number",
+    "name",
+    "employee_image_uri",
+    "gender",
+    "department_id",
+    "employment_status",
+    "hire_date",
+    "salary",
+    "created_at",
+    "updated_at",
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmployeeCursorPage {
+    data: Vec<serde_json::Value>,
+    next_cursor: Option<String>,
+}
+
+/// Merge-patch semantics: omitted fields are left unchanged, and the optional
+/// `employee_image_uri`, `hire_date` and `salary` are cleared by sending `null`.
+/// Like `NewEmployee`, fields also accept their camelCase names.
 #[derive(Deserialize, Validate)]
 pub struct EmployeeUpdate {
     #[validate(length(min = 5, max = 33))]
+    #[serde(alias = "identityNumber")]
     identity_number: Option<String>,
     #[validate(length(min = 4, max = 33))]
     name: Option<String>,
     #[validate(url)]
-    employee_image_uri: Option<String>,
+    #[serde(default, deserialize_with = "utils::patch::double_option", alias = "employeeImageUri")]
+    employee_image_uri: Option<Option<String>>,
     #[validate(custom = "validate_gender")]
     gender: Option<String>,
     #[validate(length(min = 36, max = 36))]
+    #[serde(alias = "departmentId")]
     department_id: Option<String>,
+    #[validate(custom = "validate_employment_status")]
+    #[serde(alias = "employmentStatus")]
+    employment_status: Option<String>,
+    #[serde(default, deserialize_with = "utils::patch::double_option", alias = "hireDate")]
+    hire_date: Option<Option<NaiveDate>>,
+    #[validate(range(min = 0))]
+    #[serde(default, deserialize_with = "utils::patch::double_option")]
+    salary: Option<Option<i64>>,
+}
+
+/// With `REQUIRE_EMPLOYEE_IMAGE`, every employee must keep an image URI.
+fn employee_image_required() -> actix_web::Error {
+    utils::response::field_error(
+        StatusCode::BAD_REQUEST,
+        "employee_image_uri",
+        "required",
+        "employee_image_uri is required",
+    )
+}
+
+/// With `REJECT_FUTURE_HIRE_DATES`, hire dates after today (UTC) are refused.
+fn check_hire_date(hire_date: Option<NaiveDate>) -> Result<(), actix_web::Error> {
+    let in_future = hire_date.is_some_and(|date| date > Utc::now().date_naive());
+    if in_future && config::env_flag("REJECT_FUTURE_HIRE_DATES") {
+        return Err(utils::response::field_error(
+            StatusCode::BAD_REQUEST,
+            "hire_date",
+            "must not be in the future",
+            "hire_date must not be in the future",
+        ));
+    }
+    Ok(())
+}
+
+impl EmployeeUpdate {
+    /// True when every provided field already holds the stored value.
+    fn is_noop(&self, employee: &Employee, department_id: Option<Uuid>) -> bool {
+        self.identity_number.as_ref().is_none_or(|value| *value == employee.identity_number)
+            && self.name.as_ref().is_none_or(|value| *value == employee.name)
+            && self.employee_image_uri.as_ref().is_none_or(|value| *value == employee.employee_image_uri)
+            && self.gender.as_ref().is_none_or(|value| *value == employee.gender)
+            && department_id.is_none_or(|value| value == employee.department_id)
+            && self.employment_status.as_ref().is_none_or(|value| *value == employee.employment_status)
+            && self.hire_date.is_none_or(|value| value == employee.hire_date)
+            && self.salary.is_none_or(|value| value == employee.salary)
+    }
+}
+
+fn map_validation_error(req: &HttpRequest, err: validator::ValidationErrors) -> actix_web::Error {
+    let locale = utils::i18n::Locale::from_request(req);
+    actix_web::error::ErrorBadRequest(utils::i18n::format_validation_errors(&err, locale))
+}
+
+/// Appends `WHERE` before the first filter and `AND` before the rest.
+fn push_condition(query_builder: &mut QueryBuilder<'_, Postgres>, has_conditions: &mut bool) {
+    query_builder.push(if *has_conditions { " AND " } else { " WHERE " });
+    *has_conditions = true;
+}
+
+/// Applies the listing filters shared by the page and count queries.
+/// Returns whether a `WHERE` clause was started.
+fn push_employee_filters(query_builder: &mut QueryBuilder<'_, Postgres>, query: &EmployeeQueryParams) -> bool {
+    let mut has_conditions = false;
+
+    if let Some(identity_number) = &query.identity_number {
+        push_condition(query_builder, &mut has_conditions);
+        query_builder.push("identity_number LIKE ").push_bind(format!("{}%", identity_number));
+    }
+    if let Some(name) = &query.name {
+        push_condition(query_builder, &mut has_conditions);
+        query_builder.push("name LIKE ").push_bind(format!("%{}%", name));
+    }
+    if let Some(gender) = &query.gender {
+        push_condition(query_builder, &mut has_conditions);
+        query_builder.push("gender = ").push_bind(gender.clone());
+    }
+    if let Some(department_id) = &query.department_id {
+        push_condition(query_builder, &mut has_conditions);
+        query_builder.push("department_id = ").push_bind(department_id.clone()).push("::uuid");
+    }
+    if let Some(employment_status) = &query.employment_status {
+        push_condition(query_builder, &mut has_conditions);
+        query_builder.push("employment_status = ").push_bind(employment_status.clone());
+    }
+
+    has_conditions
+}
+
+/// Encodes the last seen row as `<created_at in microseconds>_<employee_id>`.
+fn encode_cursor(employee: &Employee) -> String {
+    format!("{}_{}", employee.created_at.timestamp_micros(), employee.employee_id)
+}
+
+fn decode_cursor(cursor: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let (micros, employee_id) = cursor.split_once('_')?;
+    let created_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+    let employee_id = Uuid::parse_str(employee_id).ok()?;
+    Some((created_at, employee_id))
+}
+
+/// Ensures the department exists and is not archived before assigning employees to it.
+async fn ensure_department_assignable(pool: &sqlx::PgPool, department_id: Uuid) -> Result<(), actix_web::Error> {
+    let status = sqlx::query_scalar!("SELECT status FROM departments WHERE department_id = $1", department_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(errors::internal_error)?
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid department ID"))?;
+
+    if status == STATUS_ARCHIVED {
+        return Err(actix_web::error::ErrorConflict("Department is archived"));
+    }
+    Ok(())
+}
+
+/// With `UNIQUE_NAME_PER_DEPT`, rejects a name already used (case-insensitively) by another
+/// employee of the department. `exclude` is the employee being updated.
+async fn ensure_name_unique_in_department(
+    pool: &sqlx::PgPool,
+    name: &str,
+    department_id: Uuid,
+    exclude: Option<Uuid>,
+) -> Result<(), actix_web::Error> {
+    if !config::env_flag("UNIQUE_NAME_PER_DEPT") {
+        return Ok(());
+    }
+
+    let taken = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM employees
+            WHERE department_id = $1
+              AND LOWER(name) = LOWER($2)
+              AND ($3::UUID IS NULL OR employee_id != $3)
+        )
+        "#,
+        department_id,
+        name,
+        exclude
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(errors::internal_error)?
+    .unwrap_or(false);
+
+    if taken {
+        return Err(actix_web::error::ErrorConflict("An employee with this name already exists in the department"));
+    }
+    Ok(())
 }
 
 fn validate_gender(gender: &str) -> Result<(), validator::ValidationError> {
@@ -62,21 +268,42 @@ fn validate_gender(gender: &str) -> Result<(), validator::ValidationError> {
     Ok(())
 }
 
+fn validate_employment_status(status: &str) -> Result<(), validator::ValidationError> {
+    if !EMPLOYMENT_STATUSES.contains(&status) {
+        return Err(validator::ValidationError::new("Employment status must be one of 'active', 'on_leave' or 'terminated'"));
+    }
+    Ok(())
+}
+
 pub async fn create_employee(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
-    new_employee: web::Json<NewEmployee>,
+    mut new_employee: web::Json<NewEmployee>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // Trim the name so padding neither counts toward its length nor gets stored
+    new_employee.name = utils::text::normalize_name(&new_employee.name);
     new_employee.validate()
-        .map_err(|err| actix_web::error::ErrorBadRequest(err.to_string()))?;
+        .map_err(|err| map_validation_error(&req, err))?;
 
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1));
+    if new_employee.employee_image_uri.is_none() && config::env_flag("REQUIRE_EMPLOYEE_IMAGE") {
+        return Err(employee_image_required());
+    }
+
+    check_hire_date(new_employee.hire_date)?;
+
+    if let Some(uri) = &new_employee.employee_image_uri {
+        utils::validation::validate_image_url(uri)?;
+    }
+
+    let token = utils::jwt::extract_token(&req);
 
     if let Some(token) = token {
-        let _claims = utils::jwt::validate_token(token)
-            .map_err(|err| actix_web::error::ErrorUnauthorized(err.to_string()))?;
+        let claims = utils::jwt::validate_token(&token)
+            .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+        if new_employee.salary.is_some() && !claims.is_admin() {
+            return Err(actix_web::error::ErrorForbidden("Only admins can set salary"));
+        }
 
         // Check if the identity_number already exists
         if sqlx::query_scalar!(
@@ -85,7 +312,7 @@ pub async fn create_employee(
         )
         .fetch_one(&**pool)
         .await
-        .map_err(|err| actix_web::error::ErrorInternalServerError(err.to_string()))?
+        .map_err(errors::internal_error)?
         .unwrap_or(false)
         {
             return Err(actix_web::error::ErrorConflict("Identity number already exists"));
@@ -95,25 +322,33 @@ pub async fn create_employee(
         let department_id = Uuid::parse_str(&new_employee.department_id)
             .map_err(|_| actix_web::error::ErrorBadRequest("Invalid department ID"))?;
 
+        ensure_department_assignable(&pool, department_id).await?;
+        ensure_name_unique_in_department(&pool, &new_employee.name, department_id, None).await?;
+
         // Convert chrono::DateTime<Utc> to OffsetDateTime
         let now = Utc::now();
 
         let employee_id = Uuid::new_v4();
+        let employment_status = new_employee.employment_status.clone()
+            .unwrap_or_else(|| EMPLOYMENT_ACTIVE.to_string());
 
         sqlx::query!(
-            "INSERT INTO employees (employee_id, identity_number, name, employee_image_uri, gender, department_id, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            "INSERT INTO employees (employee_id, identity_number, name, employee_image_uri, gender, department_id, employment_status, hire_date, salary, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
             employee_id,
             &new_employee.identity_number,
             &new_employee.name,
             new_employee.employee_image_uri,
             &new_employee.gender,
             department_id, // Use parsed Uuid
+            &employment_status,
+            new_employee.hire_date,
+            new_employee.salary,
             now,           // Use OffsetDateTime
             now            // Use OffsetDateTime
         )
         .execute(&**pool)
         .await
-        .map_err(|err| actix_web::error::ErrorInternalServerError(err.to_string()))?;
+        .map_err(errors::internal_error)?;
 
         Ok(HttpResponse::Created().json(EmployeeResponse {
             identity_number: new_employee.identity_number.clone(),
@@ -121,81 +356,182 @@ pub async fn create_employee(
             employee_image_uri: new_employee.employee_image_uri.clone(),
             gender: new_employee.gender.clone(),
             department_id: new_employee.department_id.clone(),
+            employment_status,
+            hire_date: new_employee.hire_date,
+            salary: new_employee.salary,
         }))
     } else {
-        Err(actix_web::error::ErrorUnauthorized("Missing token"))?
+        Err(actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?
     }
 }
 
 pub async fn get_employees(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
+    app_config: web::Data<Config>,
     query: web::Query<EmployeeQueryParams>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1));
+    let token = utils::jwt::extract_token(&req);
 
     if let Some(token) = token {
-        let _claims = utils::jwt::validate_token(token)
-            .map_err(|err| actix_web::error::ErrorUnauthorized(err.to_string()))?;
+        let claims = utils::jwt::validate_token(&token)
+            .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
 
-        let mut query_builder: sqlx::QueryBuilder<'_, sqlx::Postgres> =
-            sqlx::QueryBuilder::new("SELECT * FROM employees");
+        // An empty `cursor` requests the first page in cursor mode
+        let cursor = match query.cursor.as_deref() {
+            None | Some("") => None,
+            Some(cursor) => Some(
+                decode_cursor(cursor).ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid cursor"))?,
+            ),
+        };
 
-        let mut params: Vec<String> = Vec::new();
-
-        if let Some(identity_number) = &query.identity_number {
-            query_builder.push(" WHERE identity_number LIKE $1");
-            params.push(format!("{}%", identity_number));
+        if query.cursor.is_some() && query.offset.is_some() {
+            return Err(actix_web::error::ErrorBadRequest("cursor and offset cannot be combined"));
         }
-        if let Some(name) = &query.name {
-            if !params.is_empty() {
-                query_builder.push(" AND name LIKE $2");
-            } else {
-                query_builder.push(" WHERE name LIKE $1");
-            }
-            params.push(format!("%{}%", name));
-        }
-        if let Some(gender) = &query.gender {
-            if !params.is_empty() {
-                query_builder.push(" AND gender = $");
-                params.push(gender.clone());
-            } else {
-                query_builder.push(" WHERE gender = $1");
-                params.push(gender.clone());
+
+        let fields = utils::fields::FieldSelection::parse(query.fields.as_deref(), EMPLOYEE_FIELDS)?;
+
+        let expand_department = match query.expand.as_deref() {
+            None | Some("") => false,
+            Some("department") => true,
+            Some(other) => {
+                return Err(actix_web::error::ErrorBadRequest(format!(
+                    "Unknown expand value '{}' (allowed: {})",
+                    other,
+                    EXPANDABLE.join(", ")
+                )));
             }
+        };
+
+        if let Some(employment_status) = &query.employment_status {
+            validate_employment_status(employment_status)
+                .map_err(|_| actix_web::error::ErrorBadRequest("Invalid employment status"))?;
         }
-        if let Some(department_id) = &query.department_id {
-            if !params.is_empty() {
-                query_builder.push(" AND department_id = $");
-                params.push(department_id.clone());
-            } else {
-                query_builder.push(" WHERE department_id = $1");
-                params.push(department_id.clone());
+
+        // Reject a malformed ID here rather than letting the `::uuid` cast fail in Postgres
+        let department_id = query
+            .department_id
+            .as_deref()
+            .map(|department_id| {
+                Uuid::parse_str(department_id)
+                    .map_err(|_| actix_web::error::ErrorBadRequest("Invalid department ID"))
+            })
+            .transpose()?;
+
+        // In strict mode, filtering on a department that doesn't exist is a 404 rather than `[]`
+        if config::env_flag("STRICT_FILTERS") {
+            if let Some(department_id) = department_id {
+                let exists = sqlx::query_scalar!(
+                    "SELECT EXISTS(SELECT 1 FROM departments WHERE department_id = $1)",
+                    department_id
+                )
+                .fetch_one(&**pool)
+                .await
+                .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?
+                .unwrap_or(false);
+
+                if !exists {
+                    return Err(actix_web::error::ErrorNotFound("Department not found"));
+                }
             }
         }
 
-        query_builder.push(" ORDER BY created_at DESC");
+        let mut query_builder: QueryBuilder<'_, Postgres> =
+            QueryBuilder::new("SELECT * FROM employees");
+        let mut has_conditions = push_employee_filters(&mut query_builder, &query);
+
+        // Keyset pagination: resume strictly after the last row of the previous page
+        if let Some((created_at, employee_id)) = cursor {
+            push_condition(&mut query_builder, &mut has_conditions);
+            query_builder
+                .push("(created_at, employee_id) < (")
+                .push_bind(created_at)
+                .push(", ")
+                .push_bind(employee_id)
+                .push(")");
+        }
+        // Cursors encode (created_at, employee_id), so cursor mode always pages newest-first
+        let order = if query.cursor.is_some() {
+            ListOrder::CreatedDesc
+        } else {
+            app_config.default_order
+        };
+        query_builder.push(order.order_by("employee_id"));
 
         if let Some(limit) = query.limit {
-            query_builder.push(format!(" LIMIT {}", limit));
+            query_builder.push(" LIMIT ").push_bind(limit);
         }
 
         if let Some(offset) = query.offset {
-            query_builder.push(format!(" OFFSET {}", offset));
+            query_builder.push(" OFFSET ").push_bind(offset);
         }
 
-        let sql = query_builder.sql(); // Get the SQL query string
-
-        let employees = sqlx::query_as::<_, Employee>(sql) // Pass the SQL query string
+        let employees = query_builder
+            .build_query_as::<Employee>()
             .fetch_all(&**pool)
             .await
             .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?;
 
+        // A full page means there may be more rows after the last one
+        let next_cursor = match query.limit {
+            Some(limit) if employees.len() as i64 == limit => employees.last().map(encode_cursor),
+            _ => None,
+        };
+
+        // Resolve department names in one query rather than joining, which would make the
+        // shared filter columns (`name`, `created_at`) ambiguous
+        let department_names: HashMap<Uuid, String> = if expand_department {
+            let department_ids = employees.iter().map(|employee| employee.department_id).collect::<Vec<_>>();
+            sqlx::query!(
+                "SELECT department_id, name FROM departments WHERE department_id = ANY($1)",
+                &department_ids
+            )
+            .fetch_all(&**pool)
+            .await
+            .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?
+            .into_iter()
+            .map(|department| (department.department_id, department.name))
+            .collect()
+        } else {
+            HashMap::new()
+        };
+
+        let employees = employees
+            .into_iter()
+            .map(|employee| {
+                let department_name = department_names.get(&employee.department_id).cloned();
+                let mut employee = fields.apply(&employee.redact_salary(claims.is_admin()));
+                if let (true, Some(employee)) = (expand_department, employee.as_object_mut()) {
+                    employee.insert("departmentName".to_string(), json!(department_name));
+                }
+                employee
+            })
+            .collect::<Vec<_>>();
+
+        if query.cursor.is_some() {
+            return Ok(HttpResponse::Ok().json(EmployeeCursorPage {
+                data: employees,
+                next_cursor,
+            }));
+        }
+
+        if query.envelope.unwrap_or(false) {
+            let mut count_builder: QueryBuilder<'_, Postgres> =
+                QueryBuilder::new("SELECT COUNT(*) FROM employees");
+            push_employee_filters(&mut count_builder, &query);
+
+            let total: i64 = count_builder
+                .build_query_scalar()
+                .fetch_one(&**pool)
+                .await
+                .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?;
+
+            return Ok(HttpResponse::Ok().json(Page::new(employees, query.limit, query.offset, total)));
+        }
+
         Ok(HttpResponse::Ok().json(employees))
     } else {
-        Err(actix_web::error::ErrorUnauthorized("Missing token"))?
+        Err(actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?
     }
 }
 
@@ -203,87 +539,119 @@ pub async fn update_employee(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
     identity_number: web::Path<String>,
-    updates: web::Json<EmployeeUpdate>,
+    mut updates: web::Json<EmployeeUpdate>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    // Trim the name so padding neither counts toward its length nor gets stored
+    if let Some(name) = &mut updates.name {
+        *name = utils::text::normalize_name(name);
+    }
     updates.validate()
-        .map_err(|err| actix_web::error::ErrorBadRequest(err.to_string()))?;
+        .map_err(|err| map_validation_error(&req, err))?;
 
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1));
+    check_hire_date(updates.hire_date.flatten())?;
+
+    if let Some(Some(uri)) = &updates.employee_image_uri {
+        utils::validation::validate_image_url(uri)?;
+    }
+
+    let token = utils::jwt::extract_token(&req);
 
     if let Some(token) = token {
-        let _claims = utils::jwt::validate_token(token)
-            .map_err(|err| actix_web::error::ErrorUnauthorized(err.to_string()))?;
+        let claims = utils::jwt::validate_token(&token)
+            .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+        if updates.salary.is_some() && !claims.is_admin() {
+            return Err(actix_web::error::ErrorForbidden("Only admins can set salary"));
+        }
 
         let identity_number = identity_number.into_inner();
 
-        let employee = sqlx::query!("SELECT * FROM employees WHERE identity_number = $1", identity_number)
+        let employee = sqlx::query_as!(Employee, "SELECT * FROM employees WHERE identity_number = $1", identity_number)
             .fetch_optional(&**pool)
             .await
-            .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?;
+            .map_err(|_| actix_web::error::ErrorInternalServerError("Query failed"))?
+            .ok_or_else(|| actix_web::error::ErrorNotFound("Employee not found"))?;
 
-        if employee.is_none() {
-            return Err(actix_web::error::ErrorNotFound("Employee not found"))?;
+        // The image may not be cleared, and an employee without one must be given one
+        if config::env_flag("REQUIRE_EMPLOYEE_IMAGE") {
+            let image_after_update = match &updates.employee_image_uri {
+                Some(uri) => uri.is_some(),
+                None => employee.employee_image_uri.is_some(),
+            };
+            if !image_after_update {
+                return Err(employee_image_required());
+            }
         }
 
-        let mut query = "UPDATE employees SET".to_string();
-        let mut params: Vec<String> = Vec::new();
-        let mut set_clauses = Vec::new();
+        let department_id = match &updates.department_id {
+            Some(department_id) => Some(
+                Uuid::parse_str(department_id)
+                    .map_err(|_| actix_web::error::ErrorBadRequest("Invalid department ID"))?,
+            ),
+            None => None,
+        };
+
+        // Nothing would change, so skip the write and keep `updated_at` as it is
+        if updates.is_noop(&employee, department_id) {
+            let employee = employee.redact_salary(claims.is_admin());
+            return Ok(HttpResponse::Ok().json(utils::patch::with_changes(&employee, &employee)));
+        }
+
+        // Transfers are only allowed into active departments
+        if let Some(department_id) = department_id.filter(|id| *id != employee.department_id) {
+            ensure_department_assignable(&pool, department_id).await?;
+        }
+
+        // Renames and transfers must not collide with a colleague's name
+        let name = updates.name.as_deref().unwrap_or(&employee.name);
+        let target_department = department_id.unwrap_or(employee.department_id);
+        if name != employee.name || target_department != employee.department_id {
+            ensure_name_unique_in_department(&pool, name, target_department, Some(employee.employee_id)).await?;
+        }
+
+        let mut query_builder: QueryBuilder<'_, Postgres> = QueryBuilder::new("UPDATE employees SET ");
+        let mut set_clauses = query_builder.separated(", ");
 
         if let Some(identity_number) = &updates.identity_number {
-            set_clauses.push("identity_number = $1".to_string());
-            params.push(identity_number.clone());
+            set_clauses.push("identity_number = ").push_bind_unseparated(identity_number.clone());
         }
         if let Some(name) = &updates.name {
-            set_clauses.push("name = $2".to_string());
-            params.push(name.clone());
+            set_clauses.push("name = ").push_bind_unseparated(name.clone());
         }
         if let Some(employee_image_uri) = &updates.employee_image_uri {
-            set_clauses.push("employee_image_uri = $3".to_string());
-            params.push(employee_image_uri.clone());
+            set_clauses.push("employee_image_uri = ").push_bind_unseparated(employee_image_uri.clone());
         }
         if let Some(gender) = &updates.gender {
-            set_clauses.push("gender = $4".to_string());
-            params.push(gender.clone());
+            set_clauses.push("gender = ").push_bind_unseparated(gender.clone());
+        }
+        if let Some(department_id) = department_id {
+            set_clauses.push("department_id = ").push_bind_unseparated(department_id);
+        }
+        if let Some(employment_status) = &updates.employment_status {
+            set_clauses.push("employment_status = ").push_bind_unseparated(employment_status.clone());
         }
-        if let Some(department_id) = &updates.department_id {
-            set_clauses.push("department_id = $5".to_string());
-            params.push(department_id.clone());
+        if let Some(hire_date) = updates.hire_date {
+            set_clauses.push("hire_date = ").push_bind_unseparated(hire_date);
         }
+        if let Some(salary) = updates.salary {
+            set_clauses.push("salary = ").push_bind_unseparated(salary);
+        }
+        set_clauses.push("updated_at = ").push_bind_unseparated(Utc::now());
 
-        let now = Utc::now();
-        set_clauses.push("updated_at = $6".to_string());
-        params.push(now.to_string());
-
-        query.push_str(&set_clauses.join(", "));
-        query.push_str(" WHERE identity_number = $7");
-        params.push(identity_number.clone());
-
-        sqlx::query(&query)
-            .bind(&params[0])
-            .bind(&params[1])
-            .bind(&params[2])
-            .bind(&params[3])
-            .bind(&params[4])
-            .bind(&params[5])
-            .bind(&params[6])
-            .execute(&**pool)
+        query_builder.push(" WHERE identity_number = ").push_bind(identity_number);
+        query_builder.push(" RETURNING *");
+
+        let updated_employee = query_builder
+            .build_query_as::<Employee>()
+            .fetch_one(&**pool)
             .await
             .map_err(|_| actix_web::error::ErrorInternalServerError("Update failed"))?;
 
-        let updated_employee = sqlx::query_as!(
-            Employee,
-            "SELECT * FROM employees WHERE identity_number = $1",
-            identity_number
-        )
-        .fetch_one(&**pool)
-        .await
-        .map_err(|_| actix_web::error::ErrorNotFound("Employee not found"))?;
-
-        Ok(HttpResponse::Ok().json(updated_employee))
+        let previous = employee.redact_salary(claims.is_admin());
+        let updated_employee = updated_employee.redact_salary(claims.is_admin());
+        Ok(HttpResponse::Ok().json(utils::patch::with_changes(&previous, &updated_employee)))
     } else {
-        Err(actix_web::error::ErrorUnauthorized("Missing token"))?
+        Err(actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?
     }
 }
 
@@ -292,13 +660,11 @@ pub async fn delete_employee(
     pool: web::Data<sqlx::PgPool>,
     identity_number: web::Path<String>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1));
+    let token = utils::jwt::extract_token(&req);
 
     if let Some(token) = token {
-        let _claims = utils::jwt::validate_token(token)
-            .map_err(|err| actix_web::error::ErrorUnauthorized(err.to_string()))?;
+        let _claims = utils::jwt::validate_token(&token)
+            .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
 
         let identity_number = identity_number.into_inner();
 
@@ -316,10 +682,567 @@ pub async fn delete_employee(
             .await
             .map_err(|_| actix_web::error::ErrorInternalServerError("Delete failed"))?;
 
-        Ok(HttpResponse::Ok().json(json!({
-            "message": "Employee deleted successfully",
-        })))
+        Ok(utils::response::deleted("Employee deleted successfully"))
     } else {
-        Err(actix_web::error::ErrorUnauthorized("Missing token"))?
+        Err(actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?
+    }
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::{json, Value};
+    use std::io::Write;
+    use uuid::Uuid;
+    use crate::models::user::{ROLE_ADMIN, ROLE_MEMBER};
+    use crate::test_support::{authed, set_env, test_app, test_pool, token_for, TestDb};
+
+    /// A valid create body for `department_id`, with a unique identity number.
+    fn new_employee(department_id: Uuid) -> Value {
+        json!({
+            "identity_number": &Uuid::new_v4().simple().to_string()[..12],
+            "name": "Test Employee",
+            "gender": "female",
+            "department_id": department_id,
+        })
+    }
+
+    fn get(uri: &str) -> test::TestRequest {
+        authed(test::TestRequest::get().uri(uri), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+    }
+
+    fn employee_ids(employees: &Value) -> Vec<Uuid> {
+        employees
+            .as_array()
+            .expect("employees are an array")
+            .iter()
+            .map(|employee| employee["employee_id"].as_str().unwrap().parse().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn members_cannot_set_salary() {
+        let app = test::init_service(test_app(test_pool())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let mut body = new_employee(Uuid::new_v4());
+        body["salary"] = json!(500_000);
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(body);
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = authed(test::TestRequest::patch().uri("/v1/employee/1234567"), &token)
+            .set_json(json!({ "salary": 500_000 }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(test::read_body(resp).await, "Only admins can set salary");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn admins_can_set_salary() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Salary {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let mut body = new_employee(department_id);
+        body["salary"] = json!(500_000);
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token_for(Uuid::new_v4(), ROLE_ADMIN))
+            .set_json(body);
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["salary"], 500_000);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn salary_is_only_listed_for_admins() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Salary {}", Uuid::new_v4())).await;
+        let employee_id = db.seed_employee(department_id, "male", "active").await;
+        sqlx::query!("UPDATE employees SET salary = 500000 WHERE employee_id = $1", employee_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/employee?department_id={}", department_id);
+        let list = |role| authed(test::TestRequest::get().uri(&uri), &token_for(Uuid::new_v4(), role)).to_request();
+
+        let employees: Value = test::call_and_read_body_json(&app, list(ROLE_MEMBER)).await;
+        assert_eq!(employees.as_array().unwrap().len(), 1);
+        assert!(employees[0].get("salary").is_none(), "salary leaked: {}", employees[0]);
+
+        let employees: Value = test::call_and_read_body_json(&app, list(ROLE_ADMIN)).await;
+        assert_eq!(employees[0]["salary"], 500_000);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn unique_names_are_enforced_per_department() {
+        let _env = set_env(&[("UNIQUE_NAME_PER_DEPT", Some("true"))]).await;
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Names {}", Uuid::new_v4())).await;
+        let other_department_id = db.seed_department(&format!("Names {}", Uuid::new_v4())).await;
+        db.seed_employee(department_id, "male", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        // `new_employee` reuses the seeded employee's name, differing here only in case
+        let mut body = new_employee(department_id);
+        body["name"] = json!("TEST EMPLOYEE");
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(body);
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(new_employee(other_department_id));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn strict_filters_reject_unknown_departments_but_not_empty_ones() {
+        let _env = set_env(&[("STRICT_FILTERS", Some("true"))]).await;
+        let mut db = TestDb::new().await;
+        let empty_department_id = db.seed_department(&format!("Empty {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let uri = format!("/v1/employee?department_id={}", Uuid::new_v4());
+        let resp = test::call_service(&app, authed(test::TestRequest::get().uri(&uri), &token).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let uri = format!("/v1/employee?department_id={}", empty_department_id);
+        let employees: Value =
+            test::call_and_read_body_json(&app, authed(test::TestRequest::get().uri(&uri), &token).to_request()).await;
+        assert_eq!(employees, json!([]));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn cursor_pages_cover_every_employee_once() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Paging {}", Uuid::new_v4())).await;
+        let mut seeded = Vec::new();
+        for _ in 0..5 {
+            seeded.push(db.seed_employee(department_id, "male", "active").await);
+        }
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let mut seen = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let uri = format!("/v1/employee?department_id={}&limit=2&cursor={}", department_id, cursor);
+            let page: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+            seen.extend(employee_ids(&page["data"]));
+            match page["nextCursor"].as_str() {
+                Some(next) => cursor = next.to_string(),
+                None => break,
+            }
+        }
+
+        let mut unique = seen.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), seen.len(), "duplicates in {:?}", seen);
+        seeded.sort();
+        assert_eq!(unique, seeded);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn archived_departments_take_no_new_employees() {
+        let mut db = TestDb::new().await;
+        let archived = db.seed_department(&format!("Archived {}", Uuid::new_v4())).await;
+        let active = db.seed_department(&format!("Active {}", Uuid::new_v4())).await;
+        let employee_id = db.seed_employee(active, "male", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let req = authed(test::TestRequest::post().uri(&format!("/v1/department/{}/archive", archived)), &token);
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::OK);
+
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(new_employee(archived));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::CONFLICT);
+
+        // Transfers in are blocked too; the employee stays where they were
+        let identity_number = employee_id.simple().to_string();
+        let req = authed(test::TestRequest::patch().uri(&format!("/v1/employee/{}", identity_number)), &token)
+            .set_json(json!({ "department_id": archived }));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::CONFLICT);
+        let department_id = sqlx::query_scalar!("SELECT department_id FROM employees WHERE employee_id = $1", employee_id)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(department_id, active);
+    }
+
+    #[actix_web::test]
+    async fn rejects_unknown_employment_statuses() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let resp = test::call_service(&app, get("/v1/employee?employment_status=retired").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(test::read_body(resp).await, "Invalid employment status");
+
+        let mut body = new_employee(Uuid::new_v4());
+        body["employment_status"] = json!("retired");
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(body);
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn filters_by_employment_status() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Status {}", Uuid::new_v4())).await;
+        db.seed_employee(department_id, "male", "active").await;
+        let on_leave = db.seed_employee(department_id, "female", "on_leave").await;
+        db.seed_employee(department_id, "female", "terminated").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/employee?department_id={}&employment_status=on_leave", department_id);
+        let employees: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+
+        assert_eq!(employee_ids(&employees), vec![on_leave]);
+        assert_eq!(employees[0]["employment_status"], "on_leave");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn listings_are_bare_unless_an_envelope_is_requested() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Envelope {}", Uuid::new_v4())).await;
+        for _ in 0..3 {
+            db.seed_employee(department_id, "male", "active").await;
+        }
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/employee?department_id={}&limit=2", department_id);
+        let bare: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert_eq!(bare.as_array().map(Vec::len), Some(2));
+
+        let uri = format!("/v1/employee?department_id={}&limit=2&offset=1&envelope=true", department_id);
+        let page: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert_eq!(page["data"].as_array().map(Vec::len), Some(2));
+        assert_eq!(page["meta"], json!({ "limit": 2, "offset": 1, "total": 3 }));
+    }
+
+    #[actix_web::test]
+    async fn rejects_script_and_data_image_uris() {
+        let app = test::init_service(test_app(test_pool())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        for uri in ["javascript:alert(1)", "data:image/png;base64,iVBORw0KGgo="] {
+            let mut body = new_employee(Uuid::new_v4());
+            body["employee_image_uri"] = json!(uri);
+            let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(body);
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+
+            let req = authed(test::TestRequest::patch().uri("/v1/employee/1234567"), &token)
+                .set_json(json!({ "employee_image_uri": uri }));
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    /// A `POST /v1/employee` whose JSON body is sent gzip-compressed.
+    fn gzip_post(body: &Value, token: &str) -> test::TestRequest {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.to_string().as_bytes()).unwrap();
+
+        authed(test::TestRequest::post().uri("/v1/employee"), token)
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .insert_header((header::CONTENT_ENCODING, "gzip"))
+            .set_payload(encoder.finish().unwrap())
+    }
+
+    #[actix_web::test]
+    async fn gzip_bodies_are_decompressed_before_parsing() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        // The salary check runs on the parsed body, before any query
+        let mut body = new_employee(Uuid::new_v4());
+        body["salary"] = json!(1);
+        let resp = test::call_service(&app, gzip_post(&body, &token_for(Uuid::new_v4(), ROLE_MEMBER)).to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(test::read_body(resp).await, "Only admins can set salary");
+    }
+
+    #[actix_web::test]
+    async fn gzip_bodies_are_capped_after_decompression() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        // A few hundred bytes on the wire, well over MAX_JSON_BODY_SIZE once inflated
+        let mut body = new_employee(Uuid::new_v4());
+        body["name"] = json!("a".repeat(1_000_000));
+        let resp = test::call_service(&app, gzip_post(&body, &token_for(Uuid::new_v4(), ROLE_MEMBER)).to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn creates_an_employee_from_a_gzip_body() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Gzip {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let body = new_employee(department_id);
+        let resp = test::call_service(&app, gzip_post(&body, &token_for(Uuid::new_v4(), ROLE_MEMBER)).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["identity_number"], body["identity_number"]);
     }
-}
\ No newline at end of file
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn stores_and_returns_the_hire_date() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Hiring {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let mut body = new_employee(department_id);
+        body["hire_date"] = json!("2021-03-15");
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(&body);
+        let created: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(created["hire_date"], "2021-03-15");
+
+        let uri = format!("/v1/employee/{}", body["identity_number"].as_str().unwrap());
+        let req = authed(test::TestRequest::patch().uri(&uri), &token).set_json(json!({ "hireDate": "2022-01-03" }));
+        let updated: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(updated["hire_date"], "2022-01-03");
+
+        let mut invalid = new_employee(department_id);
+        invalid["hire_date"] = json!("2021-02-30");
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(&invalid);
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn rejects_unknown_fields() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let resp = test::call_service(&app, get("/v1/employee?fields=name,password").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body["error"].as_str().unwrap().starts_with("Unknown fields: password"), "{}", body);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn lists_only_the_requested_fields() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Fields {}", Uuid::new_v4())).await;
+        db.seed_employee(department_id, "female", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/employee?department_id={}&fields=employee_id,name", department_id);
+        let employees: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+
+        let employee = employees[0].as_object().expect("one employee is listed");
+        let mut keys = employee.keys().map(String::as_str).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, ["employee_id", "name"]);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn department_names_are_only_listed_when_expanded() {
+        let mut db = TestDb::new().await;
+        let name = format!("Expand {}", Uuid::new_v4());
+        let department_id = db.seed_department(&name).await;
+        db.seed_employee(department_id, "female", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = format!("/v1/employee?department_id={}", department_id);
+        let lean: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert!(lean[0].get("departmentName").is_none(), "{}", lean);
+
+        let uri = format!("/v1/employee?department_id={}&expand=department", department_id);
+        let expanded: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+        assert_eq!(expanded[0]["departmentName"], name);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn offset_pages_are_stable_when_timestamps_tie() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Ties {}", Uuid::new_v4())).await;
+        let mut seeded = Vec::new();
+        for _ in 0..6 {
+            seeded.push(db.seed_employee(department_id, "male", "active").await);
+        }
+        // Bulk inserts share one timestamp
+        sqlx::query!("UPDATE employees SET created_at = '2025-01-01T00:00:00Z' WHERE department_id = $1", department_id)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let mut seen = Vec::new();
+        for offset in [0, 2, 4] {
+            let uri = format!("/v1/employee?department_id={}&limit=2&offset={}", department_id, offset);
+            let page: Value = test::call_and_read_body_json(&app, get(&uri).to_request()).await;
+            seen.extend(employee_ids(&page));
+        }
+
+        // Ties fall back to the primary key, newest-first like the timestamp
+        seeded.sort_by(|a, b| b.cmp(a));
+        assert_eq!(seen, seeded);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn an_identical_patch_leaves_updated_at_alone() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Unchanged {}", Uuid::new_v4())).await;
+        let employee_id = db.seed_employee(department_id, "male", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let updated_at = || sqlx::query_scalar!("SELECT updated_at FROM employees WHERE employee_id = $1", employee_id).fetch_one(&db.pool);
+        let before = updated_at().await.unwrap();
+
+        let uri = format!("/v1/employee/{}", employee_id.simple());
+        let req = authed(test::TestRequest::patch().uri(&uri), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(json!({ "name": "Test Employee", "gender": "male", "departmentId": department_id }));
+        let resp = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(updated_at().await.unwrap(), before);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn deletes_answer_according_to_the_response_mode() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Deletes {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        for (mode, status) in [(None, StatusCode::OK), (Some("no_content"), StatusCode::NO_CONTENT)] {
+            let _env = set_env(&[("DELETE_RESPONSE_MODE", mode)]).await;
+            let employee_id = db.seed_employee(department_id, "female", "active").await;
+
+            let uri = format!("/v1/employee/{}", employee_id.simple());
+            let resp = test::call_service(&app, authed(test::TestRequest::delete().uri(&uri), &token).to_request()).await;
+
+            assert_eq!(resp.status(), status, "{:?}", mode);
+        }
+    }
+
+    #[actix_web::test]
+    async fn required_images_are_enforced_on_create() {
+        let _env = set_env(&[("REQUIRE_EMPLOYEE_IMAGE", Some("true")), ("STRUCTURED_FIELD_ERRORS", None)]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token_for(Uuid::new_v4(), ROLE_MEMBER))
+            .set_json(new_employee(Uuid::new_v4()));
+        let resp = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(test::read_body(resp).await, "employee_image_uri is required");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn images_are_optional_unless_required() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Photos {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let body = new_employee(department_id);
+        {
+            let _env = set_env(&[("REQUIRE_EMPLOYEE_IMAGE", None)]).await;
+            let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(&body);
+            assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::CREATED);
+        }
+
+        // Once required, an employee stored without an image must be given one
+        let _env = set_env(&[("REQUIRE_EMPLOYEE_IMAGE", Some("true")), ("STRUCTURED_FIELD_ERRORS", None)]).await;
+        let uri = format!("/v1/employee/{}", body["identity_number"].as_str().unwrap());
+        let req = authed(test::TestRequest::patch().uri(&uri), &token).set_json(json!({ "name": "Renamed Employee" }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(test::read_body(resp).await, "employee_image_uri is required");
+
+        let req = authed(test::TestRequest::patch().uri(&uri), &token)
+            .set_json(json!({ "employeeImageUri": "https://cdn.example.com/photo.png" }));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn future_hire_dates_are_rejected_when_configured() {
+        let _env = set_env(&[("REJECT_FUTURE_HIRE_DATES", Some("true")), ("STRUCTURED_FIELD_ERRORS", Some("true"))]).await;
+        let today = chrono::Utc::now().date_naive();
+        assert!(super::check_hire_date(Some(today)).is_ok());
+        assert!(super::check_hire_date(None).is_ok());
+
+        let app = test::init_service(test_app(test_pool())).await;
+        let mut body = new_employee(Uuid::new_v4());
+        body["hire_date"] = json!(today + chrono::Days::new(1));
+        let req = authed(test::TestRequest::post().uri("/v1/employee"), &token_for(Uuid::new_v4(), ROLE_MEMBER)).set_json(body);
+        let resp = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body, json!({ "fields": { "hire_date": ["must not be in the future"] } }));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn creates_from_snake_case_and_camel_case_bodies() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Casing {}", Uuid::new_v4())).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let snake_case = new_employee(department_id);
+        let camel_case = json!({
+            "identityNumber": &Uuid::new_v4().simple().to_string()[..12],
+            "name": "Test Employee",
+            "gender": "female",
+            "departmentId": department_id,
+            "employeeImageUri": "https://cdn.example.com/photo.png",
+            "employmentStatus": "on_leave",
+            "hireDate": "2024-06-01",
+        });
+
+        for body in [snake_case, camel_case] {
+            let req = authed(test::TestRequest::post().uri("/v1/employee"), &token).set_json(&body);
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::CREATED, "{}", body);
+
+            let created: Value = test::read_body_json(resp).await;
+            assert_eq!(created["department_id"], json!(department_id));
+        }
+
+        let created: Value = test::call_and_read_body_json(&app, get(&format!("/v1/employee?department_id={}&employment_status=on_leave", department_id)).to_request()).await;
+        assert_eq!(created[0]["hire_date"], "2024-06-01");
+        assert_eq!(created[0]["employee_image_uri"], "https://cdn.example.com/photo.png");
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_non_uuid_department_filter() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        for department_id in ["not-a-uuid", "1234", "00000000-0000-0000-0000-00000000000g"] {
+            let uri = format!("/v1/employee?department_id={}", department_id);
+            let resp = test::call_service(&app, get(&uri).to_request()).await;
+
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", department_id);
+            assert_eq!(test::read_body(resp).await, "Invalid department ID");
+        }
+    }
+}
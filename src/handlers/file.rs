@@ -1,54 +1,220 @@
 use actix_web::{web, HttpResponse, HttpRequest, Error};
 use aws_sdk_s3::Client as S3Client;
 use uuid::Uuid;
+use crate::config::{self, Config};
 use crate::utils;
+use crate::utils::scanner::{FileScanner, ScanResult};
+use crate::utils::upload_limiter::UploadLimiter;
 use std::env;
-use serde_json::json;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use crate::db;
+use crate::errors::{self, AppError};
+use crate::models::file::{File, FILE_CONFIRMED, FILE_PENDING};
+use crate::models::pagination::Page;
 use actix_multipart::Multipart;
 use futures_util::StreamExt;
 use log::{info, error};
+use md5::{Digest, Md5};
+use base64::Engine;
+use aws_sdk_s3::presigning::PresigningConfig;
 
 use infer; // Add this import
 
+#[derive(Deserialize)]
+pub struct FileQueryParams {
+    #[serde(rename = "type")]
+    content_type: Option<String>,
+    created_after: Option<DateTime<Utc>>,
+    created_before: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    envelope: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignRequest {
+    count: usize,
+    #[serde(alias = "content_type")]
+    content_type: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresignedUpload {
+    file_id: Uuid,
+    /// The client PUTs the file here with the requested `Content-Type`
+    upload_url: String,
+    uri: String,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmRequest {
+    #[serde(alias = "fileId")]
+    file_id: Uuid,
+}
+
+/// Content types that presigned uploads may declare, with the extension they are stored under
+const PRESIGN_CONTENT_TYPES: &[(&str, &str)] = &[("image/jpeg", "jpg"), ("image/png", "png")];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    content_type: Option<String>,
+    content_length: Option<i64>,
+    #[serde(serialize_with = "crate::utils::date_format::serialize_option")]
+    last_modified: Option<DateTime<Utc>>,
+}
+
+/// Applies the listing filters shared by the page and count queries.
+fn push_file_filters(query_builder: &mut QueryBuilder<'_, Postgres>, user_id: Uuid, query: &FileQueryParams) {
+    query_builder.push(" WHERE user_id = ").push_bind(user_id);
+    // Presigned uploads only show up once confirmed
+    query_builder.push(" AND status = ").push_bind(FILE_CONFIRMED);
+
+    if let Some(content_type) = &query.content_type {
+        query_builder.push(" AND content_type = ").push_bind(content_type.to_lowercase());
+    }
+    if let Some(created_after) = query.created_after {
+        query_builder.push(" AND created_at >= ").push_bind(created_after);
+    }
+    if let Some(created_before) = query.created_before {
+        query_builder.push(" AND created_at < ").push_bind(created_before);
+    }
+}
+
+/// Uses the caller's `X-Request-Id` when it is a plain token, otherwise a fresh UUID.
+fn request_id(req: &HttpRequest) -> String {
+    req.headers()
+        .get("X-Request-Id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= 128
+                && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Reads the stored object back and checks its ETag and size against what was sent.
+/// The ETag is only the MD5 for single-part uploads without SSE-KMS, which is how
+/// `upload_file` stores objects.
+async fn verify_upload(
+    s3_client: &S3Client,
+    bucket: &str,
+    key: &str,
+    expected_md5: &str,
+    expected_size: i64,
+) -> Result<(), Error> {
+    let head = s3_client.head_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|err| {
+            error!("Failed to verify upload {}: {:?}", key, err);
+            actix_web::error::ErrorInternalServerError("Failed to verify upload")
+        })?;
+
+    let etag = head.e_tag().map(|etag| etag.trim_matches('"'));
+    if etag != Some(expected_md5) || head.content_length() != Some(expected_size) {
+        error!(
+            "Upload verification mismatch for {}: etag {:?} (expected {}), size {:?} (expected {})",
+            key, etag, expected_md5, head.content_length(), expected_size
+        );
+        return Err(actix_web::error::ErrorInternalServerError("Uploaded file failed verification"));
+    }
+
+    Ok(())
+}
+
 pub async fn upload_file(
     req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
     s3_client: web::Data<S3Client>,
+    scanner: web::Data<dyn FileScanner>,
+    upload_limiter: web::Data<UploadLimiter>,
+    app_config: web::Data<Config>,
     payload: web::Payload,
 ) -> Result<HttpResponse, Error> {
     // Extract and validate JWT token
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.strip_prefix("Bearer "))
+    let token = utils::jwt::extract_token(&req)
         .ok_or_else(|| {
-            error!("Missing or invalid token");
-            actix_web::error::ErrorUnauthorized("Missing or invalid token")
+            error!("Missing or malformed token");
+            actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE)
         })?;
 
     info!("Token: {:?}", token);
 
     // Validate the token
-    utils::jwt::validate_token(token)
+    let claims = utils::jwt::validate_token(&token)
         .map_err(|err| {
             error!("Invalid token: {:?}", err);
-            actix_web::error::ErrorUnauthorized("Invalid token")
+            actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE)
         })?;
 
+    let user_id = claims.user_id().map_err(|err| {
+        error!("Invalid user ID in token: {}", claims.sub);
+        err
+    })?;
+
+    // Uploads are buffered in memory, so cap how many each user can run at once.
+    // The permit is released when the handler returns.
+    let _upload_permit = upload_limiter.try_acquire(user_id).ok_or_else(|| {
+        error!("Too many concurrent uploads for user {}", user_id);
+        actix_web::error::ErrorTooManyRequests("Too many concurrent uploads")
+    })?;
+
     // Parse multipart form-data
     let mut multipart = Multipart::new(&req.headers(), payload);
     let mut file_data = Vec::new();
     let mut file_size = 0;
 
+    // Limits that stop clients from flooding the parser with parts
+    let max_parts = app_config.multipart_max_parts;
+    let max_field_name_len = app_config.multipart_max_field_name_len;
+    let max_total_size = app_config.multipart_max_total_size;
+    let mut part_count = 0;
+    let mut total_size = 0;
+    let mut file_received = false;
+
+    let field_names = &app_config.upload_field_names;
+
     while let Some(item) = multipart.next().await {
         let mut field = item.map_err(|err| {
             error!("Invalid multipart field: {:?}", err);
             actix_web::error::ErrorBadRequest("Invalid multipart field")
         })?;
 
-        // Ensure the field name is "file"
-        if field.name() != "file" {
-            error!("Invalid field name: expected 'file'");
-            return Err(actix_web::error::ErrorBadRequest("Invalid field name: expected 'file'"));
+        part_count += 1;
+        if part_count > max_parts {
+            error!("Multipart request exceeds {} parts", max_parts);
+            return Err(actix_web::error::ErrorBadRequest(format!("Too many multipart parts (max {})", max_parts)));
+        }
+
+        if field.name().len() > max_field_name_len {
+            error!("Multipart field name exceeds {} characters", max_field_name_len);
+            return Err(actix_web::error::ErrorBadRequest("Multipart field name too long"));
+        }
+
+        // Ensure the field name is one of the accepted names
+        if !field_names.iter().any(|name| name == field.name()) {
+            let expected = field_names.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(" or ");
+            error!("Invalid field name '{}': expected {}", field.name(), expected);
+            return Err(actix_web::error::ErrorBadRequest(format!("Invalid field name: expected {}", expected)));
+        }
+
+        // Each request stores exactly one file; a second part must not be appended to the first
+        if file_received {
+            error!("Multiple file fields in one request");
+            return Err(actix_web::error::ErrorBadRequest("Only one file field is allowed per request"));
         }
+        file_received = true;
 
         // Process file chunks
         while let Some(chunk) = field.next().await {
@@ -56,6 +222,11 @@ pub async fn upload_file(
                 error!("Failed to read chunk: {:?}", err);
                 actix_web::error::ErrorBadRequest("Failed to read chunk")
             })?;
+            total_size += chunk.len();
+            if total_size > max_total_size {
+                error!("Multipart request exceeds {} bytes", max_total_size);
+                return Err(actix_web::error::ErrorBadRequest("Multipart request too large"));
+            }
             file_size += chunk.len();
             if file_size > 102400 { // 100 KiB limit
                 error!("File size exceeds 100KiB limit");
@@ -86,28 +257,91 @@ pub async fn upload_file(
         return Err(actix_web::error::ErrorBadRequest("Only JPEG, JPG, and PNG files are allowed"));
     }
 
+    // Scan the file before it becomes publicly available
+    let scanner = scanner.into_inner();
+    let (scan_result, file_data) = web::block(move || (scanner.scan(&file_data), file_data))
+        .await
+        .map_err(|err| {
+            error!("File scanner task failed: {:?}", err);
+            actix_web::error::ErrorInternalServerError("File scan failed")
+        })?;
+
+    match scan_result {
+        ScanResult::Clean => {}
+        ScanResult::Infected(signature) => {
+            error!("Upload rejected by file scanner: {}", signature);
+            return Err(actix_web::error::ErrorBadRequest("File failed security scan"));
+        }
+        ScanResult::Error(err) => {
+            error!("File scan failed: {}", err);
+            return Err(actix_web::error::ErrorInternalServerError("File scan failed"));
+        }
+    }
+
+    // Reject images whose dimensions would break layouts, when enabled
+    if config::env_flag("VALIDATE_IMAGE_DIMENSIONS") {
+        utils::image_processing::DimensionLimits::from_env()
+            .check(&file_data)
+            .map_err(|reason| {
+                error!("Image dimensions rejected: {}", reason);
+                actix_web::error::ErrorBadRequest(reason)
+            })?;
+    }
+
+    // Optionally normalize uploads to WebP; images that already are WebP are stored as-is
+    let mut mime_type = file_type.mime_type();
+    let convert_to_webp = env::var("CONVERT_UPLOADS_TO")
+        .map(|format| format.eq_ignore_ascii_case("webp"))
+        .unwrap_or(false);
+
+    let file_data = if convert_to_webp && mime_type != "image/webp" {
+        let converted = web::block(move || utils::image_processing::convert_to_webp(&file_data))
+            .await
+            .map_err(|err| {
+                error!("Image conversion task failed: {:?}", err);
+                actix_web::error::ErrorInternalServerError("Image conversion failed")
+            })?
+            .map_err(|err| {
+                error!("Image conversion failed: {}", err);
+                actix_web::error::ErrorBadRequest("Image could not be converted")
+            })?;
+        mime_type = "image/webp";
+        converted
+    } else {
+        file_data
+    };
+
     // Generate unique filename
     let file_id = Uuid::new_v4();
-    let extension = match file_type.mime_type() {
+    let extension = match mime_type {
         "image/jpeg" => "jpg",
         "image/jpg" => "jpg",
         "image/png" => "png",
+        "image/webp" => "webp",
         _ => "bin", // Fallback, though validation should prevent this
     };
     let file_name = format!("{}.{}", file_id, extension);
 
-    info!("Uploading to S3: {}", file_name);
+    // Tag the object so an orphan left by a failed flow can be traced back to its request
+    let request_id = request_id(&req);
+
+    info!("Uploading to S3: {} (request {})", file_name, request_id);
+
+    // S3 rejects the upload if the body it receives doesn't match this digest
+    let digest = Md5::digest(&file_data);
+    let content_md5 = base64::engine::general_purpose::STANDARD.encode(&digest);
+    let stored_size = file_data.len() as i64;
 
     // Upload to S3
-    let bucket_name = env::var("AWS_S3_BUCKET")
-        .map_err(|err| {
-            error!("AWS_S3_BUCKET environment variable not set: {:?}", err);
-            actix_web::error::ErrorInternalServerError("AWS_S3_BUCKET not set")
-        })?;
+    let bucket_name = &app_config.s3_bucket;
 
     s3_client.put_object()
-        .bucket(&bucket_name)
+        .bucket(bucket_name)
         .key(&file_name)
+        .content_type(mime_type)
+        .content_md5(content_md5)
+        .metadata("request-id", &request_id)
+        .metadata("user-id", user_id.to_string())
         .body(file_data.into())
         .send()
         .await
@@ -116,9 +350,796 @@ pub async fn upload_file(
             actix_web::error::ErrorInternalServerError("Failed to upload file")
         })?;
 
+    if config::env_flag("VERIFY_UPLOAD") {
+        verify_upload(&s3_client, bucket_name, &file_name, &format!("{:x}", digest), stored_size).await?;
+    }
+
     // Construct S3 URL
     let s3_url = format!("https://{}.s3.amazonaws.com/{}", bucket_name, file_name);
 
+    // Persist the file record so clients can reference it immediately
+    let file = sqlx::query_as!(
+        File,
+        "INSERT INTO files (file_id, user_id, uri, content_type, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        file_id,
+        user_id,
+        s3_url,
+        mime_type,
+        Utc::now()
+    )
+    .fetch_one(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to save file record: {:?}", err);
+        actix_web::error::ErrorInternalServerError("Failed to save file record")
+    })?;
+
     // Return JSON response
-    Ok(HttpResponse::Ok().json(json!({ "uri": s3_url })))
-}
\ No newline at end of file
+    Ok(HttpResponse::Ok().json(file))
+}
+
+/// Issues presigned PUT URLs so clients can upload straight to S3, bypassing the request
+/// body limits. Each URL gets a pending `files` row that the client confirms after uploading.
+pub async fn presign_uploads(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    s3_client: web::Data<S3Client>,
+    app_config: web::Data<Config>,
+    body: web::Json<PresignRequest>,
+) -> Result<HttpResponse, Error> {
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?;
+
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+    let user_id = claims.user_id()?;
+
+    let max_count = app_config.max_presigned_uploads;
+    if body.count == 0 || body.count > max_count {
+        return Err(actix_web::error::ErrorBadRequest(format!("count must be between 1 and {}", max_count)));
+    }
+
+    let content_type = body.content_type.trim().to_lowercase();
+    let extension = PRESIGN_CONTENT_TYPES
+        .iter()
+        .find(|(allowed, _)| *allowed == content_type)
+        .map(|(_, extension)| *extension)
+        .ok_or_else(|| actix_web::error::ErrorBadRequest("Only JPEG and PNG files are allowed"))?;
+
+    let expires_in = app_config.presign_expiry;
+    let presigning_config = PresigningConfig::expires_in(expires_in)
+        .map_err(errors::internal_error)?;
+    let expires_at = Utc::now() + expires_in;
+
+    let bucket_name = &app_config.s3_bucket;
+    let mut uploads = Vec::with_capacity(body.count);
+    for _ in 0..body.count {
+        let file_id = Uuid::new_v4();
+        let file_name = format!("{}.{}", file_id, extension);
+
+        let presigned = s3_client.put_object()
+            .bucket(bucket_name)
+            .key(&file_name)
+            .content_type(&content_type)
+            .presigned(presigning_config.clone())
+            .await
+            .map_err(|err| {
+                error!("Failed to presign upload: {:?}", err);
+                actix_web::error::ErrorInternalServerError("Failed to presign upload")
+            })?;
+
+        uploads.push(PresignedUpload {
+            file_id,
+            upload_url: presigned.uri().to_string(),
+            uri: format!("https://{}.s3.amazonaws.com/{}", bucket_name, file_name),
+            expires_at,
+        });
+    }
+
+    // Record every pending file or none of them
+    let rows = uploads.iter().map(|upload| (upload.file_id, upload.uri.clone())).collect::<Vec<_>>();
+    db::with_transaction(&pool, move |tx| Box::pin(async move {
+        let now = Utc::now();
+        for (file_id, uri) in rows {
+            sqlx::query!(
+                "INSERT INTO files (file_id, user_id, uri, content_type, status, created_at) VALUES ($1, $2, $3, $4, $5, $6)",
+                file_id,
+                user_id,
+                uri,
+                content_type,
+                FILE_PENDING,
+                now
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok::<_, AppError>(())
+    }))
+    .await?;
+
+    Ok(HttpResponse::Created().json(uploads))
+}
+
+/// Marks a presigned upload as confirmed once its object is in S3 with the declared
+/// content type and within `MAX_PRESIGNED_UPLOAD_SIZE`. Confirming twice is a no-op.
+pub async fn confirm_upload(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    s3_client: web::Data<S3Client>,
+    app_config: web::Data<Config>,
+    body: web::Json<ConfirmRequest>,
+) -> Result<HttpResponse, Error> {
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?;
+
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+    let user_id = claims.user_id()?;
+
+    let file = sqlx::query_as!(
+        File,
+        "SELECT * FROM files WHERE file_id = $1 AND user_id = $2",
+        body.file_id,
+        user_id
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to load file record: {:?}", err);
+        actix_web::error::ErrorInternalServerError("Failed to load file")
+    })?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("File not found"))?;
+
+    if file.status == FILE_CONFIRMED {
+        return Ok(HttpResponse::Ok().json(file));
+    }
+
+    // Objects are stored under the last path segment of their URL
+    let key = file.uri.rsplit('/').next().unwrap_or_default();
+
+    let head = s3_client.head_object()
+        .bucket(&app_config.s3_bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|err| {
+            if err.as_service_error().is_some_and(|err| err.is_not_found()) {
+                return actix_web::error::ErrorBadRequest("File has not been uploaded");
+            }
+            error!("Failed to read S3 metadata for {}: {:?}", key, err);
+            actix_web::error::ErrorInternalServerError("Failed to read file metadata")
+        })?;
+
+    let max_size = app_config.max_presigned_upload_size;
+    if head.content_length().unwrap_or(0).max(0) as u64 > max_size {
+        error!("Presigned upload {} exceeds {} bytes", key, max_size);
+        return Err(actix_web::error::ErrorBadRequest(format!("File size exceeds {} bytes", max_size)));
+    }
+
+    if head.content_type() != Some(file.content_type.as_str()) {
+        error!("Presigned upload {} has content type {:?}, expected {}", key, head.content_type(), file.content_type);
+        return Err(actix_web::error::ErrorBadRequest("File content type does not match the presigned type"));
+    }
+
+    let file = sqlx::query_as!(
+        File,
+        "UPDATE files SET status = $1 WHERE file_id = $2 AND user_id = $3 RETURNING *",
+        FILE_CONFIRMED,
+        file.file_id,
+        user_id
+    )
+    .fetch_one(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to confirm file record: {:?}", err);
+        actix_web::error::ErrorInternalServerError("Failed to confirm file")
+    })?;
+
+    Ok(HttpResponse::Ok().json(file))
+}
+
+/// Lists the caller's uploads, newest first.
+pub async fn list_files(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    query: web::Query<FileQueryParams>,
+) -> Result<HttpResponse, Error> {
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?;
+
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+    let user_id = claims.user_id()?;
+
+    if query.limit.is_some_and(|limit| limit < 0) || query.offset.is_some_and(|offset| offset < 0) {
+        return Err(actix_web::error::ErrorBadRequest("limit and offset must not be negative"));
+    }
+
+    let mut query_builder = QueryBuilder::new("SELECT * FROM files");
+    push_file_filters(&mut query_builder, user_id, &query);
+    query_builder.push(" ORDER BY created_at DESC, file_id DESC");
+
+    if let Some(limit) = query.limit {
+        query_builder.push(" LIMIT ").push_bind(limit);
+    }
+    if let Some(offset) = query.offset {
+        query_builder.push(" OFFSET ").push_bind(offset);
+    }
+
+    let files = query_builder
+        .build_query_as::<File>()
+        .fetch_all(&**pool)
+        .await
+        .map_err(|err| {
+            error!("Failed to list files: {:?}", err);
+            actix_web::error::ErrorInternalServerError("Failed to list files")
+        })?;
+
+    // Wrap the listing in `{ data, meta }` only when the client opts in
+    if query.envelope.unwrap_or(false) {
+        let mut count_builder = QueryBuilder::new("SELECT COUNT(*) FROM files");
+        push_file_filters(&mut count_builder, user_id, &query);
+
+        let total: i64 = count_builder
+            .build_query_scalar()
+            .fetch_one(&**pool)
+            .await
+            .map_err(|err| {
+                error!("Failed to count files: {:?}", err);
+                actix_web::error::ErrorInternalServerError("Failed to list files")
+            })?;
+
+        return Ok(HttpResponse::Ok().json(Page::new(files, query.limit, query.offset, total)));
+    }
+
+    Ok(HttpResponse::Ok().json(files))
+}
+
+/// Confirms an owned file still exists in S3 and returns its stored metadata.
+/// Files owned by someone else are reported as missing.
+pub async fn get_file_metadata(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    s3_client: web::Data<S3Client>,
+    app_config: web::Data<Config>,
+    file_id: web::Path<String>,
+) -> Result<HttpResponse, Error> {
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized(utils::jwt::MISSING_TOKEN_MESSAGE))?;
+
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| actix_web::error::ErrorUnauthorized(utils::jwt::INVALID_TOKEN_MESSAGE))?;
+
+    let user_id = claims.user_id()?;
+
+    let file_id = Uuid::parse_str(&file_id.into_inner())
+        .map_err(|_| actix_web::error::ErrorBadRequest("Invalid file ID"))?;
+
+    let file = sqlx::query_as!(
+        File,
+        "SELECT * FROM files WHERE file_id = $1 AND user_id = $2",
+        file_id,
+        user_id
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|err| {
+        error!("Failed to load file record: {:?}", err);
+        actix_web::error::ErrorInternalServerError("Failed to load file")
+    })?
+    .ok_or_else(|| actix_web::error::ErrorNotFound("File not found"))?;
+
+    // Objects are stored under the last path segment of their URL
+    let key = file.uri.rsplit('/').next().unwrap_or_default();
+
+    let head = s3_client.head_object()
+        .bucket(&app_config.s3_bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|err| {
+            if err.as_service_error().is_some_and(|err| err.is_not_found()) {
+                return actix_web::error::ErrorNotFound("File not found");
+            }
+            error!("Failed to read S3 metadata for {}: {:?}", key, err);
+            actix_web::error::ErrorInternalServerError("Failed to read file metadata")
+        })?;
+
+    Ok(HttpResponse::Ok().json(FileMetadata {
+        content_type: head.content_type().map(str::to_string),
+        content_length: head.content_length(),
+        last_modified: head
+            .last_modified()
+            .and_then(|modified| DateTime::from_timestamp(modified.secs(), modified.subsec_nanos())),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
+    use aws_sdk_s3::operation::put_object::PutObjectOutput;
+    use aws_sdk_s3::types::error::NotFound;
+    use aws_smithy_mocks_experimental::mock;
+    use chrono::{DateTime, TimeZone, Utc};
+    use md5::{Digest, Md5};
+    use serde_json::{json, Value};
+    use futures_util::future::{join, join_all};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::Duration;
+    use uuid::Uuid;
+    use crate::app::{self, AppState};
+    use crate::models::file::{FILE_CONFIRMED, FILE_PENDING};
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{self, authed, multipart, set_env, test_app, test_config, test_pool, token_for, unreachable_pool, TestDb};
+    use crate::utils::scanner::{FileScanner, ScanResult};
+
+    /// Posts `parts` to `/v1/file` as a member, returning the status and body.
+    async fn send(state: &AppState, parts: &[(&str, &[u8])]) -> (StatusCode, String) {
+        send_as(state, Uuid::new_v4(), parts).await
+    }
+
+    async fn send_as(state: &AppState, user_id: Uuid, parts: &[(&str, &[u8])]) -> (StatusCode, String) {
+        let app = test::init_service(app::build_app(state)).await;
+
+        let token = token_for(user_id, ROLE_MEMBER);
+        let req = multipart(authed(test::TestRequest::post().uri("/v1/file"), &token), parts);
+        let resp = test::call_service(&app, req.to_request()).await;
+        let status = resp.status();
+        let body = test::read_body(resp).await;
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    async fn upload(field_names: &str, parts: &[(&str, &[u8])]) -> (StatusCode, String) {
+        let mut config = test_config();
+        config.upload_field_names = field_names.split(',').map(str::to_string).collect();
+        send(&test_support::state_with(config, test_pool()), parts).await
+    }
+
+    /// A black PNG of the given size.
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .expect("failed to encode test PNG");
+        bytes
+    }
+
+    /// State whose S3 client accepts every upload and reports `etag` when it is read back.
+    /// The database is unreachable, so an upload that passes every check ends with
+    /// "Failed to save file record".
+    fn state_with_stored_etag(etag: String, size: usize) -> AppState {
+        let put = mock!(aws_sdk_s3::Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let head = mock!(aws_sdk_s3::Client::head_object).then_output(move || {
+            HeadObjectOutput::builder()
+                .e_tag(format!("\"{}\"", etag))
+                .content_length(size as i64)
+                .build()
+        });
+
+        let mut state = test_support::state_with(test_config(), unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put, &head]);
+        state
+    }
+
+    #[actix_web::test]
+    async fn accepts_a_configured_alternative_field_name() {
+        // Plain text gets past the field check and fails on the type check instead
+        let (status, body) = upload("file,image", &[("image", b"not an image")]).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Unable to detect file type");
+    }
+
+    #[actix_web::test]
+    async fn rejects_an_unknown_field_name() {
+        let (status, body) = upload("file", &[("attachment", b"not an image")]).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Invalid field name: expected 'file'");
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_second_file_part() {
+        let (status, body) = upload("file", &[("file", b"first"), ("file", b"second")]).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Only one file field is allowed per request");
+    }
+
+    #[actix_web::test]
+    async fn verification_rejects_a_stored_object_with_another_etag() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", Some("true")),
+            ("CONVERT_UPLOADS_TO", None),
+            ("VALIDATE_IMAGE_DIMENSIONS", None),
+        ])
+        .await;
+        let image = png(4, 4);
+
+        let state = state_with_stored_etag("0123456789abcdef0123456789abcdef".to_string(), image.len());
+        let (status, body) = send(&state, &[("file", &image)]).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body, "Uploaded file failed verification");
+    }
+
+    #[actix_web::test]
+    async fn verification_accepts_a_stored_object_with_the_uploaded_md5() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", Some("true")),
+            ("CONVERT_UPLOADS_TO", None),
+            ("VALIDATE_IMAGE_DIMENSIONS", None),
+        ])
+        .await;
+        let image = png(4, 4);
+
+        let state = state_with_stored_etag(format!("{:x}", Md5::digest(&image)), image.len());
+        let (status, body) = send(&state, &[("file", &image)]).await;
+
+        // Verification passed, so the upload got as far as saving its record
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body, "Failed to save file record");
+    }
+
+    #[actix_web::test]
+    async fn tags_the_stored_object_with_the_request_and_user() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", None),
+            ("CONVERT_UPLOADS_TO", None),
+            ("VALIDATE_IMAGE_DIMENSIONS", None),
+        ])
+        .await;
+        let user_id = Uuid::new_v4();
+        let expected_user = user_id.to_string();
+        let put = mock!(aws_sdk_s3::Client::put_object)
+            .match_requests(move |req| {
+                let metadata = req.metadata().expect("object metadata");
+                metadata.get("request-id").map(String::as_str) == Some("trace-123")
+                    && metadata.get("user-id") == Some(&expected_user)
+            })
+            .then_output(|| PutObjectOutput::builder().build());
+
+        let mut state = test_support::state_with(test_config(), unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put]);
+        let app = test::init_service(app::build_app(&state)).await;
+
+        let req = authed(test::TestRequest::post().uri("/v1/file"), &token_for(user_id, ROLE_MEMBER))
+            .insert_header(("X-Request-Id", "trace-123"));
+        let resp = test::call_service(&app, multipart(req, &[("file", &png(4, 4))]).to_request()).await;
+
+        // A put without the expected metadata matches no rule and fails the upload earlier
+        assert_eq!(test::read_body(resp).await, "Failed to save file record");
+        assert_eq!(put.num_calls(), 1);
+    }
+
+    #[actix_web::test]
+    async fn converts_png_uploads_to_webp() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", None),
+            ("CONVERT_UPLOADS_TO", Some("webp")),
+            ("VALIDATE_IMAGE_DIMENSIONS", None),
+        ])
+        .await;
+        let put = mock!(aws_sdk_s3::Client::put_object)
+            .match_requests(|req| {
+                let body_type = req.body().bytes().and_then(infer::get).map(|kind| kind.mime_type());
+                req.key().is_some_and(|key| key.ends_with(".webp"))
+                    && req.content_type() == Some("image/webp")
+                    && body_type == Some("image/webp")
+            })
+            .then_output(|| PutObjectOutput::builder().build());
+
+        let mut state = test_support::state_with(test_config(), unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put]);
+        let (_, body) = send(&state, &[("file", &png(4, 4))]).await;
+
+        assert_eq!(body, "Failed to save file record");
+        assert_eq!(put.num_calls(), 1);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn lists_files_filtered_by_type_and_date_range() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let day = |month, day_of_month| Utc.with_ymd_and_hms(2024, month, day_of_month, 12, 0, 0).unwrap();
+        let january_png = db.seed_file(user_id, "image/png", day(1, 10)).await;
+        db.seed_file(user_id, "image/jpeg", day(1, 15)).await;
+        db.seed_file(user_id, "image/png", day(2, 1)).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let uri = "/v1/file?type=IMAGE/PNG&created_after=2024-01-01T00:00:00Z&created_before=2024-02-01T00:00:00Z&envelope=true";
+        let req = authed(test::TestRequest::get().uri(uri), &token_for(user_id, ROLE_MEMBER));
+        let page: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+
+        assert_eq!(page["meta"]["total"], 1);
+        assert_eq!(page["data"].as_array().unwrap().len(), 1);
+        assert_eq!(page["data"][0]["fileId"], json!(january_png));
+        assert_eq!(page["data"][0]["contentType"], "image/png");
+    }
+
+    struct InfectedScanner;
+
+    impl FileScanner for InfectedScanner {
+        fn scan(&self, _data: &[u8]) -> ScanResult {
+            ScanResult::Infected("Test.Signature".to_string())
+        }
+    }
+
+    #[actix_web::test]
+    async fn infected_uploads_are_rejected_before_reaching_s3() {
+        let put = mock!(aws_sdk_s3::Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let mut state = test_support::state_with(test_config(), unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put]);
+        state.file_scanner = Arc::new(InfectedScanner);
+
+        let (status, body) = send(&state, &[("file", &png(4, 4))]).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "File failed security scan");
+        assert_eq!(put.num_calls(), 0);
+    }
+
+    #[actix_web::test]
+    async fn rejects_requests_with_too_many_parts() {
+        let mut config = test_config();
+        config.multipart_max_parts = 1;
+        let parts = vec![("file", &b"x"[..]); 50];
+
+        let (status, body) = send(&test_support::state_with(config, test_pool()), &parts).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Too many multipart parts (max 1)");
+    }
+
+    #[actix_web::test]
+    async fn rejects_overlong_field_names() {
+        let name = "f".repeat(65);
+
+        let (status, body) = upload("file", &[(&name, b"x")]).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Multipart field name too long");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn upload_returns_the_stored_file_record() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", None),
+            ("CONVERT_UPLOADS_TO", None),
+            ("VALIDATE_IMAGE_DIMENSIONS", None),
+        ])
+        .await;
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let put = mock!(aws_sdk_s3::Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let mut state = test_support::state_with(test_config(), db.pool.clone());
+        state.s3_client = test_support::s3_mock(&[&put]);
+
+        let (status, body) = send_as(&state, user_id, &[("file", &png(4, 4))]).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+
+        let file: Value = serde_json::from_str(&body).unwrap();
+        let file_id: Uuid = file["fileId"].as_str().unwrap().parse().expect("fileId is a UUID");
+        let created_at: DateTime<Utc> = file["createdAt"].as_str().unwrap().parse().expect("createdAt is a timestamp");
+        assert!((Utc::now() - created_at).num_seconds() < 60);
+        assert_eq!(file["uri"], format!("https://test-bucket.s3.amazonaws.com/{}.png", file_id));
+        assert_eq!(file["contentType"], "image/png");
+    }
+
+    #[actix_web::test]
+    async fn dimension_checks_reject_only_out_of_bounds_images() {
+        let _env = set_env(&[
+            ("VERIFY_UPLOAD", None),
+            ("CONVERT_UPLOADS_TO", None),
+            ("VALIDATE_IMAGE_DIMENSIONS", Some("true")),
+            ("IMAGE_MAX_WIDTH", Some("100")),
+        ])
+        .await;
+        let put = mock!(aws_sdk_s3::Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let mut state = test_support::state_with(test_config(), unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put]);
+
+        let (status, body) = send(&state, &[("file", &png(101, 50))]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Image width 101px is outside the allowed range 0-100px");
+        assert_eq!(put.num_calls(), 0);
+
+        // In bounds, so the upload got as far as saving its record
+        let (status, body) = send(&state, &[("file", &png(100, 50))]).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body, "Failed to save file record");
+        assert_eq!(put.num_calls(), 1);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn metadata_comes_from_the_stored_object() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let stored = db.seed_file(user_id, "image/png", Utc::now()).await;
+        let deleted = db.seed_file(user_id, "image/png", Utc::now()).await;
+
+        let stored_key = stored.to_string();
+        let head = mock!(aws_sdk_s3::Client::head_object)
+            .match_requests(move |req| req.key() == Some(stored_key.as_str()))
+            .then_output(|| {
+                HeadObjectOutput::builder()
+                    .content_type("image/png")
+                    .content_length(2048)
+                    .last_modified(aws_sdk_s3::primitives::DateTime::from_secs(1_735_787_045))
+                    .build()
+            });
+        let missing = mock!(aws_sdk_s3::Client::head_object)
+            .then_error(|| HeadObjectError::NotFound(NotFound::builder().build()));
+        let mut state = test_support::state_with(test_config(), db.pool.clone());
+        state.s3_client = test_support::s3_mock(&[&head, &missing]);
+        let app = test::init_service(app::build_app(&state)).await;
+        let token = token_for(user_id, ROLE_MEMBER);
+        let metadata = |file_id: Uuid| {
+            authed(test::TestRequest::get().uri(&format!("/v1/file/{}/metadata", file_id)), &token).to_request()
+        };
+
+        let body: Value = test::call_and_read_body_json(&app, metadata(stored)).await;
+        assert_eq!(body, json!({
+            "contentType": "image/png",
+            "contentLength": 2048,
+            "lastModified": "2025-01-02T03:04:05Z",
+        }));
+
+        let resp = test::call_service(&app, metadata(deleted)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Another user's file is reported the same way, without asking S3
+        let calls = head.num_calls() + missing.num_calls();
+        let req = authed(test::TestRequest::get().uri(&format!("/v1/file/{}/metadata", stored)), &token_for(Uuid::new_v4(), ROLE_MEMBER));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(head.num_calls() + missing.num_calls(), calls);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn presigning_records_a_pending_file_per_url() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = authed(test::TestRequest::post().uri("/v1/file/presign"), &token_for(user_id, ROLE_MEMBER))
+            .set_json(json!({ "count": 2, "contentType": "image/png" }));
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let uploads: Value = test::read_body_json(resp).await;
+        let uploads = uploads.as_array().unwrap();
+        assert_eq!(uploads.len(), 2);
+
+        for upload in uploads {
+            let file_id: Uuid = upload["fileId"].as_str().unwrap().parse().unwrap();
+            let upload_url = upload["uploadUrl"].as_str().unwrap();
+            assert!(upload_url.contains(&format!("/{}.png?", file_id)), "{}", upload_url);
+            assert!(upload_url.contains("X-Amz-Signature="), "{}", upload_url);
+
+            let (status, content_type) = sqlx::query_as::<_, (String, String)>(
+                "SELECT status, content_type FROM files WHERE file_id = $1 AND user_id = $2",
+            )
+            .bind(file_id)
+            .bind(user_id)
+            .fetch_one(&db.pool)
+            .await
+            .expect("presigned file was recorded");
+            assert_eq!((status.as_str(), content_type.as_str()), (FILE_PENDING, "image/png"));
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn confirms_uploaded_objects_and_rejects_missing_ones() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let uploaded = db.seed_file(user_id, "image/png", Utc::now()).await;
+        let never_uploaded = db.seed_file(user_id, "image/png", Utc::now()).await;
+        sqlx::query("UPDATE files SET status = $1 WHERE file_id = ANY($2)")
+            .bind(FILE_PENDING)
+            .bind(&[uploaded, never_uploaded][..])
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let uploaded_key = uploaded.to_string();
+        let head = mock!(aws_sdk_s3::Client::head_object)
+            .match_requests(move |req| req.key() == Some(uploaded_key.as_str()))
+            .then_output(|| HeadObjectOutput::builder().content_type("image/png").content_length(1024).build());
+        let missing = mock!(aws_sdk_s3::Client::head_object)
+            .then_error(|| HeadObjectError::NotFound(NotFound::builder().build()));
+        let mut state = test_support::state_with(test_config(), db.pool.clone());
+        state.s3_client = test_support::s3_mock(&[&head, &missing]);
+        let app = test::init_service(app::build_app(&state)).await;
+        let token = token_for(user_id, ROLE_MEMBER);
+        let confirm = |file_id: Uuid| {
+            authed(test::TestRequest::post().uri("/v1/file/confirm"), &token)
+                .set_json(json!({ "fileId": file_id }))
+                .to_request()
+        };
+
+        let file: Value = test::call_and_read_body_json(&app, confirm(uploaded)).await;
+        assert_eq!(file["fileId"], json!(uploaded));
+        assert_eq!(file["status"], FILE_CONFIRMED);
+        assert_eq!(file["uri"], format!("https://test-bucket.s3.amazonaws.com/{}", uploaded));
+
+        let resp = test::call_service(&app, confirm(never_uploaded)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(test::read_body(resp).await, "File has not been uploaded");
+        let status = sqlx::query_scalar::<_, String>("SELECT status FROM files WHERE file_id = $1")
+            .bind(never_uploaded)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(status, FILE_PENDING);
+    }
+
+    /// Holds every scan until `release` is called, keeping uploads in flight.
+    #[derive(Default)]
+    struct GatedScanner {
+        entered: AtomicUsize,
+        released: Mutex<bool>,
+        opened: Condvar,
+    }
+
+    impl GatedScanner {
+        fn release(&self) {
+            *self.released.lock().unwrap() = true;
+            self.opened.notify_all();
+        }
+    }
+
+    impl FileScanner for GatedScanner {
+        fn scan(&self, _data: &[u8]) -> ScanResult {
+            self.entered.fetch_add(1, Ordering::SeqCst);
+            let mut released = self.released.lock().unwrap();
+            while !*released {
+                released = self.opened.wait(released).unwrap();
+            }
+            ScanResult::Clean
+        }
+    }
+
+    #[actix_web::test]
+    async fn uploads_beyond_the_per_user_limit_get_429() {
+        let mut config = test_config();
+        config.max_concurrent_uploads_per_user = 2;
+        let put = mock!(aws_sdk_s3::Client::put_object).then_output(|| PutObjectOutput::builder().build());
+        let mut state = test_support::state_with(config, unreachable_pool());
+        state.s3_client = test_support::s3_mock(&[&put]);
+        let scanner = Arc::new(GatedScanner::default());
+        state.file_scanner = scanner.clone();
+        let app = test::init_service(app::build_app(&state)).await;
+
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+        let image = png(4, 4);
+        let uploads = (0..4).map(|_| {
+            let req = multipart(authed(test::TestRequest::post().uri("/v1/file"), &token), &[("file", &image)]);
+            test::call_service(&app, req.to_request())
+        });
+        // Let the first two uploads finish only once they are both holding a permit
+        let release = async {
+            while scanner.entered.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            scanner.release();
+        };
+        let (responses, ()) = tokio::time::timeout(Duration::from_secs(30), join(join_all(uploads), release))
+            .await
+            .expect("uploads did not finish");
+
+        let rejected = responses.iter().filter(|resp| resp.status() == StatusCode::TOO_MANY_REQUESTS).count();
+        assert_eq!(rejected, 2);
+        assert_eq!(scanner.entered.load(Ordering::SeqCst), 2);
+    }
+}
@@ -2,4 +2,7 @@ pub mod auth;
 pub mod user;
 pub mod file;
 pub mod employee;
-pub mod department;
\ No newline at end of file
+pub mod department;
+pub mod stats;
+pub mod admin;
+pub mod root;
@@ -0,0 +1,46 @@
+use actix_web::HttpResponse;
+use serde_json::json;
+use std::env;
+
+/// Unauthenticated landing response for `GET /`, handy for health checkers.
+/// `DOCS_URL` overrides the advertised docs path; set it empty to omit it.
+pub async fn index() -> Result<HttpResponse, actix_web::Error> {
+    let docs = env::var("DOCS_URL").unwrap_or_else(|_| "/swagger".to_string());
+
+    let mut body = json!({
+        "service": "GoGoManager",
+        "status": "ok",
+    });
+    if !docs.is_empty() {
+        body["docs"] = json!(docs);
+    }
+
+    Ok(HttpResponse::Ok().json(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+    use crate::test_support::{set_env, test_app, test_pool};
+
+    #[actix_web::test]
+    async fn index_names_the_service_without_a_token() {
+        let _env = set_env(&[("DOCS_URL", None)]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let body: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(body, json!({ "service": "GoGoManager", "status": "ok", "docs": "/swagger" }));
+    }
+
+    #[actix_web::test]
+    async fn index_omits_docs_when_disabled() {
+        let _env = set_env(&[("DOCS_URL", Some(""))]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let body: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/").to_request()).await;
+
+        assert_eq!(body, json!({ "service": "GoGoManager", "status": "ok" }));
+    }
+}
@@ -0,0 +1,363 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use crate::utils;
+use crate::errors::AppError;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DepartmentEmployeeCount {
+    department_id: Uuid,
+    name: String,
+    count: i64,
+}
+
+/// Tenure in days, computed from employees that have a hire date
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TenureStats {
+    employee_count: i64,
+    average_days: Option<f64>,
+    min_days: Option<i32>,
+    max_days: Option<i32>,
+}
+
+/// Employee breakdown for a single department
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DepartmentStats {
+    department_id: Uuid,
+    employee_count: i64,
+    by_gender: BTreeMap<String, i64>,
+    by_employment_status: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize)]
+struct FacetValue {
+    value: String,
+    count: i64,
+}
+
+/// Values in use across all employees, for building filter dropdowns
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmployeeFacets {
+    gender: Vec<FacetValue>,
+    department: Vec<DepartmentEmployeeCount>,
+    employment_status: Vec<FacetValue>,
+}
+
+pub async fn employees_by_department(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::authenticate(&req)?;
+
+    // LEFT JOIN so departments without employees are reported with a zero count
+    let counts = sqlx::query_as!(
+        DepartmentEmployeeCount,
+        r#"
+        SELECT
+            d.department_id,
+            d.name,
+            COUNT(e.employee_id) AS "count!"
+        FROM departments d
+        LEFT JOIN employees e ON e.department_id = d.department_id
+        GROUP BY d.department_id, d.name
+        ORDER BY d.name, d.department_id
+        "#
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(counts))
+}
+
+pub async fn tenure(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::authenticate(&req)?;
+
+    let stats = sqlx::query_as!(
+        TenureStats,
+        r#"
+        SELECT
+            COUNT(*) AS "employee_count!",
+            AVG(CURRENT_DATE - hire_date)::FLOAT8 AS average_days,
+            MIN(CURRENT_DATE - hire_date) AS min_days,
+            MAX(CURRENT_DATE - hire_date) AS max_days
+        FROM employees
+        WHERE hire_date IS NOT NULL
+        "#
+    )
+    .fetch_one(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+pub async fn department_stats(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+    department_id: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::authenticate(&req)?;
+
+    let department_id = Uuid::parse_str(&department_id.into_inner())
+        .map_err(|_| AppError::BadRequest("Invalid department ID".to_string()))?;
+
+    // The grand-total grouping set yields a row even when no department matches
+    let exists = sqlx::query_scalar!(
+        "SELECT EXISTS(SELECT 1 FROM departments WHERE department_id = $1)",
+        department_id
+    )
+    .fetch_one(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?
+    .unwrap_or(false);
+
+    if !exists {
+        return Err(AppError::NotFound("Department not found".to_string()).into());
+    }
+
+    // One pass over the department's employees: a row per gender, a row per employment
+    // status and a grand total
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            e.gender,
+            e.employment_status,
+            GROUPING(e.gender) AS "gender_grouping!",
+            GROUPING(e.employment_status) AS "status_grouping!",
+            COUNT(e.employee_id) AS "count!"
+        FROM departments d
+        LEFT JOIN employees e ON e.department_id = d.department_id
+        WHERE d.department_id = $1
+        GROUP BY GROUPING SETS ((e.gender), (e.employment_status), ())
+        "#,
+        department_id
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    let mut stats = DepartmentStats {
+        department_id,
+        employee_count: 0,
+        by_gender: BTreeMap::new(),
+        by_employment_status: BTreeMap::new(),
+    };
+
+    // A NULL group key only comes from the LEFT JOIN of an empty department
+    for row in rows {
+        match (row.gender_grouping, row.status_grouping) {
+            (0, _) => {
+                if let Some(gender) = row.gender {
+                    stats.by_gender.insert(gender, row.count);
+                }
+            }
+            (_, 0) => {
+                if let Some(status) = row.employment_status {
+                    stats.by_employment_status.insert(status, row.count);
+                }
+            }
+            _ => stats.employee_count = row.count,
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+pub async fn employee_facets(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    utils::jwt::authenticate(&req)?;
+
+    // One pass over the employees with a grouping set per facet, most common values first.
+    // LEFT JOIN so employees whose department is gone still count towards gender and status.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            e.gender AS "gender?",
+            e.employment_status AS "employment_status?",
+            e.department_id AS "department_id?",
+            d.name AS "department_name?",
+            GROUPING(e.gender) AS "gender_grouping!",
+            GROUPING(e.employment_status) AS "status_grouping!",
+            COUNT(*) AS "count!"
+        FROM employees e
+        LEFT JOIN departments d ON d.department_id = e.department_id
+        GROUP BY GROUPING SETS ((e.gender), (e.employment_status), (e.department_id, d.name))
+        ORDER BY COUNT(*) DESC, e.gender, e.employment_status, d.name, e.department_id
+        "#
+    )
+    .fetch_all(&**pool)
+    .await
+    .map_err(|err| AppError::DatabaseError(err.to_string()))?;
+
+    let mut facets = EmployeeFacets {
+        gender: Vec::new(),
+        department: Vec::new(),
+        employment_status: Vec::new(),
+    };
+
+    for row in rows {
+        match (row.gender_grouping, row.status_grouping, row.gender, row.employment_status) {
+            (0, _, Some(gender), _) => facets.gender.push(FacetValue { value: gender, count: row.count }),
+            (_, 0, _, Some(status)) => facets.employment_status.push(FacetValue { value: status, count: row.count }),
+            _ => {
+                if let (Some(department_id), Some(name)) = (row.department_id, row.department_name) {
+                    facets.department.push(DepartmentEmployeeCount { department_id, name, count: row.count });
+                }
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(facets))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+    use uuid::Uuid;
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{authed, test_app, token_for, TestDb};
+
+    /// `[{ "value": ..., "count": ... }]` as a map, for comparing regardless of order
+    fn facet_counts(facet: &Value) -> BTreeMap<String, i64> {
+        facet
+            .as_array()
+            .expect("facet is an array")
+            .iter()
+            .map(|entry| (entry["value"].as_str().unwrap().to_string(), entry["count"].as_i64().unwrap()))
+            .collect()
+    }
+
+    async fn table_counts(db: &TestDb, column: &str) -> BTreeMap<String, i64> {
+        sqlx::query_as::<_, (String, i64)>(&format!("SELECT {0}, COUNT(*) FROM employees GROUP BY {0}", column))
+            .fetch_all(&db.pool)
+            .await
+            .expect("failed to count employees")
+            .into_iter()
+            .collect()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn facets_count_every_employee() {
+        // Compares against whole-table counts, so nothing may insert employees meanwhile
+        let mut db = TestDb::exclusive().await;
+        let department_id = db.seed_department(&format!("Facets {}", Uuid::new_v4())).await;
+        db.seed_employee(department_id, "male", "active").await;
+        db.seed_employee(department_id, "male", "on_leave").await;
+        db.seed_employee(department_id, "female", "active").await;
+
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+        let req = authed(test::TestRequest::get().uri("/v1/employee/facets"), &token).to_request();
+        let facets: Value = test::call_and_read_body_json(&app, req).await;
+
+        let department = facets["department"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|entry| entry["departmentId"] == json!(department_id))
+            .expect("seeded department is listed");
+        assert_eq!(department["count"], 3);
+
+        assert_eq!(facet_counts(&facets["gender"]), table_counts(&db, "gender").await);
+        assert_eq!(facet_counts(&facets["employmentStatus"]), table_counts(&db, "employment_status").await);
+    }
+
+    /// The `count` reported for `department_id` in a `[{ departmentId, count }]` listing.
+    fn count_for(listing: &Value, department_id: Uuid) -> Option<i64> {
+        listing
+            .as_array()?
+            .iter()
+            .find(|entry| entry["departmentId"] == json!(department_id))?["count"]
+            .as_i64()
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn employees_by_department_includes_empty_departments() {
+        let mut db = TestDb::new().await;
+        let staffed = db.seed_department(&format!("Staffed {}", Uuid::new_v4())).await;
+        let empty = db.seed_department(&format!("Empty {}", Uuid::new_v4())).await;
+        db.seed_employee(staffed, "female", "active").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = authed(test::TestRequest::get().uri("/v1/stats/employees-by-department"), &token_for(Uuid::new_v4(), ROLE_MEMBER));
+        let counts: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+
+        assert_eq!(count_for(&counts, staffed), Some(1));
+        assert_eq!(count_for(&counts, empty), Some(0));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn tenure_aggregates_hire_dates() {
+        // Compares against the totals before seeding, so nothing may change employees meanwhile
+        let mut db = TestDb::exclusive().await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+        let tenure = || authed(test::TestRequest::get().uri("/v1/stats/tenure"), &token).to_request();
+
+        let before: Value = test::call_and_read_body_json(&app, tenure()).await;
+        let department_id = db.seed_department(&format!("Tenure {}", Uuid::new_v4())).await;
+        for days in [100, 300] {
+            let employee_id = db.seed_employee(department_id, "female", "active").await;
+            sqlx::query("UPDATE employees SET hire_date = CURRENT_DATE - $1::INT WHERE employee_id = $2")
+                .bind(days)
+                .bind(employee_id)
+                .execute(&db.pool)
+                .await
+                .unwrap();
+        }
+        // Employees without a hire date are left out
+        db.seed_employee(department_id, "male", "active").await;
+        let after: Value = test::call_and_read_body_json(&app, tenure()).await;
+
+        let count_before = before["employeeCount"].as_i64().unwrap();
+        let count_after = after["employeeCount"].as_i64().unwrap();
+        assert_eq!(count_after, count_before + 2);
+
+        let total_before = before["averageDays"].as_f64().unwrap_or(0.0) * count_before as f64;
+        let total_after = after["averageDays"].as_f64().unwrap() * count_after as f64;
+        assert!((total_after - total_before - 400.0).abs() < 1e-6, "{} then {}", before, after);
+
+        assert!(after["minDays"].as_i64().unwrap() <= 100);
+        assert!(after["maxDays"].as_i64().unwrap() >= 300);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn department_stats_break_employees_down_by_gender_and_status() {
+        let mut db = TestDb::new().await;
+        let department_id = db.seed_department(&format!("Breakdown {}", Uuid::new_v4())).await;
+        db.seed_employee(department_id, "male", "active").await;
+        db.seed_employee(department_id, "female", "active").await;
+        db.seed_employee(department_id, "female", "on_leave").await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+        let token = token_for(Uuid::new_v4(), ROLE_MEMBER);
+
+        let uri = format!("/v1/department/{}/stats", department_id);
+        let stats: Value = test::call_and_read_body_json(&app, authed(test::TestRequest::get().uri(&uri), &token).to_request()).await;
+
+        assert_eq!(stats["employeeCount"], 3);
+        assert_eq!(stats["byGender"], json!({ "female": 2, "male": 1 }));
+        assert_eq!(stats["byEmploymentStatus"], json!({ "active": 2, "on_leave": 1 }));
+
+        let uri = format!("/v1/department/{}/stats", Uuid::new_v4());
+        let resp = test::call_service(&app, authed(test::TestRequest::get().uri(&uri), &token).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,58 +1,69 @@
 use actix_web::{web, HttpResponse, HttpRequest};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
-use uuid::Uuid;
 use chrono::Utc;
-use url::Url;
+use actix_web::http::StatusCode;
+use crate::config;
+use crate::db;
 use crate::utils;
+use crate::utils::fields::{FieldSelection, FieldsQuery};
 use crate::models::user::{GetUserProfileResponse, UserWithoutDates};
+use crate::models::file::File;
+use crate::utils::json_stream::spawn_json_stream;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
 use crate::errors::AppError;
-use log::{info, error};
+use log::error;
 
+/// Merge-patch semantics: omitted fields are left unchanged and `null` clears a field.
+/// Fields are camelCase; each also accepts its snake_case name.
 #[derive(Deserialize, Validate)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct UserProfileUpdate {
     #[validate(email)]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    email: Option<String>,
+    #[serde(default, deserialize_with = "utils::patch::double_option")]
+    email: Option<Option<String>>,
     #[validate(length(min = 4, max = 52))]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "utils::patch::double_option")]
+    name: Option<Option<String>>,
     #[validate(url)]
-    user_image_uri: Option<String>,
+    #[serde(default, deserialize_with = "utils::patch::double_option", alias = "user_image_uri")]
+    user_image_uri: Option<Option<String>>,
     #[validate(length(min = 4, max = 52))]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    company_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, deserialize_with = "utils::patch::double_option", alias = "company_name")]
+    company_name: Option<Option<String>>,
     #[validate(url)]
-    company_image_uri: Option<String>,
+    #[serde(default, deserialize_with = "utils::patch::double_option", alias = "company_image_uri")]
+    company_image_uri: Option<Option<String>>,
 }
 
+/// Unset fields serialize as `null`, matching `GET /v1/user`
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserProfileResponse {
     email: String,
-    name: String,
-    user_image_uri: String,
-    company_name: String,
-    company_image_uri: String,
+    name: Option<String>,
+    user_image_uri: Option<String>,
+    company_name: Option<String>,
+    company_image_uri: Option<String>,
 }
 
+/// Fields clients may select from the profile with `fields`
+const PROFILE_FIELDS: &[&str] = &["email", "name", "userImageUri", "companyName", "companyImageUri"];
+
 pub async fn get_user_profile(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
+    query: web::Query<FieldsQuery>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1))
-        .ok_or_else(|| AppError::Unauthorized("Missing token".to_string()))?;
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| AppError::Unauthorized(utils::jwt::MISSING_TOKEN_MESSAGE.to_string()))?;
+
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| AppError::Unauthorized(utils::jwt::INVALID_TOKEN_MESSAGE.to_string()))?;
 
-    let claims = utils::jwt::validate_token(token)
-        .map_err(|err| AppError::Unauthorized(err.to_string()))?;
+    let user_id = claims.user_id()?;
 
-    let user_id = Uuid::parse_str(&claims.sub)
-        .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+    let fields = FieldSelection::parse(query.fields.as_deref(), PROFILE_FIELDS)?;
 
     let user = sqlx::query_as!(
         GetUserProfileResponse,
@@ -76,7 +87,7 @@ pub async fn get_user_profile(
     })?;
 
     if let Some(user) = user {
-        Ok(HttpResponse::Ok().json(user))
+        Ok(HttpResponse::Ok().json(fields.apply(&user)))
     } else {
         Err(AppError::Unauthorized("User not found or unauthorized".to_string()).into())
     }
@@ -85,21 +96,18 @@ pub async fn get_user_profile(
 pub async fn update_user_profile(
     req: HttpRequest,
     pool: web::Data<sqlx::PgPool>,
-    updates: web::Json<UserProfileUpdate>,
+    mut updates: web::Json<UserProfileUpdate>,
 ) -> Result<HttpResponse, actix_web::Error> {
     // Check token first
-    let token = req.headers().get("Authorization")
-        .and_then(|auth| auth.to_str().ok())
-        .and_then(|auth| auth.split_whitespace().nth(1))
-        .ok_or_else(|| AppError::Unauthorized("Missing token".to_string()))?;
+    let token = utils::jwt::extract_token(&req)
+        .ok_or_else(|| AppError::Unauthorized(utils::jwt::MISSING_TOKEN_MESSAGE.to_string()))?;
 
-    let claims = utils::jwt::validate_token(token)
-        .map_err(|err| AppError::Unauthorized(err.to_string()))?;
+    let claims = utils::jwt::validate_token(&token)
+        .map_err(|_| AppError::Unauthorized(utils::jwt::INVALID_TOKEN_MESSAGE.to_string()))?;
 
-    let user_id = Uuid::parse_str(&claims.sub)
-        .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))?;
+    let user_id = claims.user_id()?;
 
-    // Check if the request contains at least one non-null field
+    // Check if the request contains at least one field
     if updates.email.is_none()
         && updates.name.is_none()
         && updates.user_image_uri.is_none()
@@ -109,167 +117,139 @@ pub async fn update_user_profile(
         return Err(AppError::BadRequest("No update fields provided".to_string()).into());
     }
 
-    // Check if any field is explicitly set to null
-    if updates.email.is_none()
-        || updates.name.is_none()
-        || updates.user_image_uri.is_none()
-        || updates.company_name.is_none()
-        || updates.company_image_uri.is_none()
-    {
-        return Err(AppError::BadRequest("Null values are not allowed".to_string()).into());
+    // Every other field may be cleared, but users must keep an email
+    if matches!(updates.email, Some(None)) {
+        return Err(AppError::BadRequest("email cannot be null".to_string()).into());
     }
+    // Trim the name so padding neither counts toward its length nor gets stored
+    if let Some(Some(name)) = &mut updates.name {
+        *name = utils::text::normalize_name(name);
+    }
+
     // Validate input fields
     updates.validate().map_err(|err| {
-        let details = err.field_errors()
-            .iter()
-            .map(|(field, errs)| {
-                let errors = errs.iter()
-                    .map(|e| format!("{}: {}", e.code, e.message.as_deref().unwrap_or("")))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("{}: [{}]", field, errors)
-            })
-            .collect::<Vec<_>>()
-            .join("; ");
+        let locale = utils::i18n::Locale::from_request(&req);
+        let details = utils::i18n::format_validation_errors(&err, locale);
         AppError::BadRequest(format!("Validation failed: {}", details))
     })?;
 
-    // Validate URLs if provided
-    if let Some(uri) = &updates.user_image_uri {
-        info!("Validating user_image_uri: {}", uri);
-        match Url::parse(uri) {
-            Ok(url) => {
-                // Additional validation for domain structure
-                if let Some(host) = url.host() {
-                    match host {
-                        url::Host::Domain(domain) => {
-                            // Ensure the domain has at least one dot (.) to be valid
-                            if !domain.contains('.') {
-                                error!("Invalid domain in user_image_uri: {}", uri);
-                                return Err(AppError::BadRequest("Invalid domain in 'user_image_uri'".to_string()).into());
-                            }
-                        }
-                        url::Host::Ipv4(_) | url::Host::Ipv6(_) => {
-                            // IP addresses are valid, so no additional checks are needed
-                        }
-                    }
-                } else {
-                    error!("Missing host in user_image_uri: {}", uri);
-                    return Err(AppError::BadRequest("Missing host in 'user_image_uri'".to_string()).into());
-                }
-                info!("user_image_uri is valid: {}", uri);
-            }
-            Err(err) => {
-                error!("Invalid user_image_uri: {}, error: {}", uri, err);
-                return Err(AppError::BadRequest("Invalid URL format in 'user_image_uri'".to_string()).into());
-            }
-        };
+    if let Some(Some(email)) = &updates.email {
+        utils::validation::validate_strict_email(email)?;
     }
 
-    if let Some(uri) = &updates.company_image_uri {
-        info!("Validating company_image_uri: {}", uri);
-        match Url::parse(uri) {
-            Ok(url) => {
-                // Additional validation for domain structure
-                if let Some(host) = url.host() {
-                    match host {
-                        url::Host::Domain(domain) => {
-                            // Ensure the domain has at least one dot (.) to be valid
-                            if !domain.contains('.') {
-                                error!("Invalid domain in company_image_uri: {}", uri);
-                                return Err(AppError::BadRequest("Invalid domain in 'company_image_uri'".to_string()).into());
-                            }
-                        }
-                        url::Host::Ipv4(_) | url::Host::Ipv6(_) => {
-                            // IP addresses are valid, so no additional checks are needed
-                        }
-                    }
-                } else {
-                    error!("Missing host in company_image_uri: {}", uri);
-                    return Err(AppError::BadRequest("Missing host in 'company_image_uri'".to_string()).into());
-                }
-                info!("company_image_uri is valid: {}", uri);
-            }
-            Err(err) => {
-                error!("Invalid company_image_uri: {}, error: {}", uri, err);
-                return Err(AppError::BadRequest("Invalid URL format in 'company_image_uri'".to_string()).into());
+    // Check both URLs before failing so every invalid field is reported together
+    let mut url_errors = Vec::new();
+    for (field, uri) in [
+        ("userImageUri", &updates.user_image_uri),
+        ("companyImageUri", &updates.company_image_uri),
+    ] {
+        if let Some(Some(uri)) = uri {
+            if let Err(err) = utils::validation::validate_image_url(uri) {
+                error!("Invalid {}: {}, error: {}", field, uri, err);
+                url_errors.push((field, err.message().to_string()));
             }
-        };
+        }
     }
 
-    // Check for duplicate email if provided
-    if let Some(email) = &updates.email {
-        let email_exists = sqlx::query_scalar!(
-            "SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = LOWER($1) AND user_id != $2)",
-            email,
+    if !url_errors.is_empty() {
+        let message = url_errors
+            .iter()
+            .map(|(field, reason)| format!("{}: {}", field, reason))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if config::env_flag("STRUCTURED_FIELD_ERRORS") {
+            return Err(utils::response::field_errors(StatusCode::BAD_REQUEST, &url_errors, &message));
+        }
+        return Err(AppError::BadRequest(message).into());
+    }
+
+    // The duplicate-email check and the write share one transaction. Locking the
+    // caller's row serializes their own concurrent edits, and the advisory lock on the
+    // lowercased email stops two users from claiming the same address at once.
+    let updates = updates.into_inner();
+    let previous = db::with_transaction(&pool, move |tx| Box::pin(async move {
+        let previous = sqlx::query_as!(
+            UserProfileResponse,
+            "SELECT email, name, user_image_uri, company_name, company_image_uri FROM users WHERE user_id = $1 FOR UPDATE",
             user_id
         )
-        .fetch_one(&**pool)
+        .fetch_optional(&mut **tx)
         .await
         .map_err(|e| {
-            log::error!("DB error during email check: {:?}", e);
+            log::error!("DB error while locking user: {:?}", e);
             AppError::InternalServerError("Database error".to_string())
-        })?;
+        })?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+        // Check for duplicate email if provided
+        if let Some(Some(email)) = &updates.email {
+            sqlx::query!("SELECT pg_advisory_xact_lock(hashtext(LOWER($1)))", email)
+                .execute(&mut **tx)
+                .await
+                .map_err(|e| {
+                    log::error!("DB error while locking email: {:?}", e);
+                    AppError::InternalServerError("Database error".to_string())
+                })?;
+
+            let email_exists = sqlx::query_scalar!(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(email) = LOWER($1) AND user_id != $2)",
+                email,
+                user_id
+            )
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(|e| {
+                log::error!("DB error during email check: {:?}", e);
+                AppError::InternalServerError("Database error".to_string())
+            })?;
 
-        if email_exists.unwrap_or(false) {
-            return Err(AppError::Conflict("Email already exists".to_string()).into());
+            if email_exists.unwrap_or(false) {
+                return Err(AppError::Conflict("Email already exists".to_string()));
+            }
         }
-    }
 
-    // Build the update query dynamically
-    let mut query = sqlx::QueryBuilder::new("UPDATE users SET");
-    let mut has_updates = false;
+        // Build the update query dynamically; `Some(None)` binds NULL to clear the column
+        let mut query = sqlx::QueryBuilder::new("UPDATE users SET ");
+        let mut set_clauses = query.separated(", ");
 
-    if let Some(email) = &updates.email {
-        query.push(" email = ").push_bind(email);
-        has_updates = true;
-    }
-    if let Some(name) = &updates.name {
-        if has_updates {
-            query.push(", ");
+        if let Some(email) = updates.email {
+            set_clauses.push("email = ").push_bind_unseparated(email);
         }
-        query.push(" name = ").push_bind(name);
-        has_updates = true;
-    }
-    if let Some(user_image_uri) = &updates.user_image_uri {
-        if has_updates {
-            query.push(", ");
+        if let Some(name) = updates.name {
+            set_clauses.push("name = ").push_bind_unseparated(name);
         }
-        query.push(" user_image_uri = ").push_bind(user_image_uri);
-        has_updates = true;
-    }
-    if let Some(company_name) = &updates.company_name {
-        if has_updates {
-            query.push(", ");
+        if let Some(user_image_uri) = updates.user_image_uri {
+            set_clauses.push("user_image_uri = ").push_bind_unseparated(user_image_uri);
         }
-        query.push(" company_name = ").push_bind(company_name);
-        has_updates = true;
-    }
-    if let Some(company_image_uri) = &updates.company_image_uri {
-        if has_updates {
-            query.push(", ");
+        if let Some(company_name) = updates.company_name {
+            set_clauses.push("company_name = ").push_bind_unseparated(company_name);
+        }
+        if let Some(company_image_uri) = updates.company_image_uri {
+            set_clauses.push("company_image_uri = ").push_bind_unseparated(company_image_uri);
         }
-        query.push(" company_image_uri = ").push_bind(company_image_uri);
-        has_updates = true;
-    }
 
-    // Only proceed if there are updates to make
-    if !has_updates {
-        return Err(AppError::BadRequest("No valid fields to update".to_string()).into());
-    }
+        // Add the updated_at field and WHERE clause
+        set_clauses.push("updated_at = ").push_bind_unseparated(Utc::now());
+        query.push(" WHERE user_id = ").push_bind(user_id);
 
-    // Add the updated_at field and WHERE clause
-    query.push(" , updated_at = ").push_bind(Utc::now());
-    query.push(" WHERE user_id = ").push_bind(user_id);
+        // Execute the query
+        query.build()
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| match &e {
+                // The exact-match UNIQUE constraint on email is the last line of defence
+                sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23505") => {
+                    AppError::Conflict("Email already exists".to_string())
+                }
+                _ => {
+                    log::error!("DB error during update: {:?}", e);
+                    AppError::InternalServerError("Update failed".to_string())
+                }
+            })?;
 
-    // Execute the query
-    query.build()
-        .execute(&**pool)
-        .await
-        .map_err(|e| {
-            log::error!("DB error during update: {:?}", e);
-            AppError::InternalServerError("Update failed".to_string())
-        })?;
+        Ok::<_, AppError>(previous)
+    }))
+    .await?;
 
     // Fetch the updated user profile
     let user = sqlx::query_as!(
@@ -285,11 +265,247 @@ pub async fn update_user_profile(
     })?;
 
     // Return updated response
-    Ok(HttpResponse::Ok().json(UserProfileResponse {
+    let updated = UserProfileResponse {
         email: user.email,
-        name: user.name.unwrap_or_default(),
-        user_image_uri: user.user_image_uri.unwrap_or_default(),
-        company_name: user.company_name.unwrap_or_default(),
-        company_image_uri: user.company_image_uri.unwrap_or_default(),
-    }))
-}
\ No newline at end of file
+        name: user.name,
+        user_image_uri: user.user_image_uri,
+        company_name: user.company_name,
+        company_image_uri: user.company_image_uri,
+    };
+    Ok(HttpResponse::Ok().json(utils::patch::with_changes(&previous, &updated)))
+}
+
+/// Streams everything stored about the caller as a downloadable JSON document:
+/// their profile and the files they uploaded. Employees and departments are shared
+/// company records without an owner, so they are not part of the export.
+pub async fn export_user_data(
+    req: HttpRequest,
+    pool: web::Data<sqlx::PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = utils::jwt::authenticate(&req)?;
+
+    let user_id = claims.user_id()?;
+
+    // Load the profile up front so a missing user is a 401 rather than a broken stream
+    let profile = sqlx::query_as!(
+        GetUserProfileResponse,
+        "SELECT email, name, user_image_uri, company_name, company_image_uri FROM users WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(&**pool)
+    .await
+    .map_err(|e| {
+        log::error!("Database error during user export: {:?}", e);
+        AppError::InternalServerError("Database error".to_string())
+    })?
+    .ok_or_else(|| AppError::Unauthorized("User not found or unauthorized".to_string()))?;
+
+    let pool = pool.into_inner();
+    let body = spawn_json_stream("User data export", move |writer| async move {
+        writer.write_raw(b"{").await?;
+        writer.write_field("profile", &profile).await?;
+        writer.write_raw(b",").await?;
+
+        let files = sqlx::query_as!(
+            File,
+            "SELECT * FROM files WHERE user_id = $1 ORDER BY created_at, file_id",
+            user_id
+        )
+        .fetch(&*pool);
+        writer.write_array_field("files", files).await?;
+
+        writer.write_raw(b"}").await
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("gogomanager-export.json".to_string())],
+        })
+        .streaming(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use chrono::Utc;
+    use futures_util::future::join;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{authed, set_env, test_app, test_pool, token_for, TestDb};
+
+    #[actix_web::test]
+    async fn get_user_profile_requires_a_token() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let res = test::call_service(&app, TestRequest::get().uri("/v1/user").to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn get_user_profile_rejects_an_invalid_token() {
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let req = authed(TestRequest::get().uri("/v1/user"), "not-a-token").to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn get_user_profile_returns_the_callers_profile() {
+        let pool = test_pool();
+        let user_id = Uuid::new_v4();
+        let email = format!("{}@example.com", user_id);
+        let now = Utc::now();
+        sqlx::query!(
+            "INSERT INTO users (user_id, email, password, name, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6)",
+            user_id,
+            email,
+            "unused",
+            "Test User",
+            now,
+            now
+        )
+        .execute(&pool)
+        .await
+        .expect("failed to seed user");
+
+        let app = test::init_service(test_app(pool.clone())).await;
+        let req = authed(TestRequest::get().uri("/v1/user"), &token_for(user_id, ROLE_MEMBER)).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["email"], email);
+        assert_eq!(body["name"], "Test User");
+
+        sqlx::query!("DELETE FROM users WHERE user_id = $1", user_id)
+            .execute(&pool)
+            .await
+            .expect("failed to clean up user");
+    }
+
+    fn profile_update(body: Value) -> TestRequest {
+        authed(TestRequest::patch().uri("/v1/user"), &token_for(Uuid::new_v4(), ROLE_MEMBER)).set_json(body)
+    }
+
+    #[actix_web::test]
+    async fn update_user_profile_rejects_script_and_data_uris() {
+        let _env = set_env(&[("STRUCTURED_FIELD_ERRORS", None), ("MAX_IMAGE_URI_LEN", None)]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        for (field, uri) in [
+            ("userImageUri", "javascript:alert(1)"),
+            ("companyImageUri", "data:image/png;base64,iVBORw0KGgo="),
+        ] {
+            let res = test::call_service(&app, profile_update(json!({ field: uri })).to_request()).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", uri);
+
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["error"], format!("{}: Only http and https URLs are allowed", field));
+        }
+    }
+
+    #[actix_web::test]
+    async fn update_user_profile_rejects_overlong_uris() {
+        let _env = set_env(&[("STRUCTURED_FIELD_ERRORS", None), ("MAX_IMAGE_URI_LEN", Some("64"))]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+
+        let uri = format!("https://example.com/{}", "a".repeat(45));
+        let res = test::call_service(&app, profile_update(json!({ "userImageUri": uri })).to_request()).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"], "userImageUri: URL must be at most 64 characters");
+    }
+
+    #[actix_web::test]
+    async fn update_user_profile_reports_every_invalid_uri_together() {
+        let body = json!({ "userImageUri": "not a url", "companyImageUri": "http://intranet/logo.png" });
+
+        {
+            let _env = set_env(&[("STRUCTURED_FIELD_ERRORS", Some("true")), ("MAX_IMAGE_URI_LEN", None)]).await;
+            let app = test::init_service(test_app(test_pool())).await;
+            let res = test::call_service(&app, profile_update(body.clone()).to_request()).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+            let errors: Value = test::read_body_json(res).await;
+            assert_eq!(errors, json!({ "fields": {
+                "userImageUri": ["Invalid URL format"],
+                "companyImageUri": ["Invalid domain in URL"],
+            } }));
+        }
+
+        let _env = set_env(&[("STRUCTURED_FIELD_ERRORS", None), ("MAX_IMAGE_URI_LEN", None)]).await;
+        let app = test::init_service(test_app(test_pool())).await;
+        let res = test::call_service(&app, profile_update(body).to_request()).await;
+        let errors: Value = test::read_body_json(res).await;
+        assert_eq!(errors["error"], "userImageUri: Invalid URL format; companyImageUri: Invalid domain in URL");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn export_includes_the_profile_and_owned_files() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let file_id = db.seed_file(user_id, "image/png", Utc::now()).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = authed(TestRequest::get().uri("/v1/user/export"), &token_for(user_id, ROLE_MEMBER)).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let disposition = res.headers().get(header::CONTENT_DISPOSITION).unwrap().to_str().unwrap();
+        assert!(disposition.starts_with("attachment"), "{}", disposition);
+
+        let export: Value = serde_json::from_slice(&test::read_body(res).await).unwrap();
+        assert_eq!(export["profile"]["email"], format!("{}@example.com", user_id));
+        assert_eq!(export["files"][0]["fileId"], json!(file_id));
+        assert_eq!(export["files"].as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn concurrent_email_changes_cannot_claim_the_same_address() {
+        let mut db = TestDb::new().await;
+        let first = db.seed_user(ROLE_MEMBER).await;
+        let second = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let email = format!("shared-{}@example.com", Uuid::new_v4().simple());
+        let change_email = |user_id: Uuid, email: String| {
+            let req = authed(TestRequest::patch().uri("/v1/user"), &token_for(user_id, ROLE_MEMBER))
+                .set_json(json!({ "email": email }));
+            test::call_service(&app, req.to_request())
+        };
+        let (a, b) = join(change_email(first, email.clone()), change_email(second, email.to_uppercase())).await;
+
+        let mut statuses = [a.status(), b.status()];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+
+        let claimed = sqlx::query_scalar!("SELECT COUNT(*) FROM users WHERE LOWER(email) = $1", email)
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(claimed, Some(1));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database at TEST_DATABASE_URL"]
+    async fn missing_image_uris_are_null_rather_than_empty() {
+        let mut db = TestDb::new().await;
+        let user_id = db.seed_user(ROLE_MEMBER).await;
+        let app = test::init_service(test_app(db.pool.clone())).await;
+
+        let req = authed(TestRequest::get().uri("/v1/user"), &token_for(user_id, ROLE_MEMBER)).to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["userImageUri"], Value::Null);
+        assert_eq!(body["companyImageUri"], Value::Null);
+        assert!(body.as_object().unwrap().contains_key("userImageUri"), "{}", body);
+    }
+}
@@ -1,71 +1,85 @@
+mod app;
 mod handlers;
 mod models;
 mod utils;
 mod db;
 mod errors;
+mod config;
+mod middleware;
+#[cfg(test)]
+mod test_support;
 
-use actix_web::{web, App, HttpServer};
+use actix_web::{web, HttpServer};
 use dotenv::dotenv;
-use sqlx::PgPool;
-use std::env;
-use log::info;
+use log::{error, info};
+use crate::app::AppState;
+use crate::config::Config;
+use crate::middleware::readiness::Readiness;
 use crate::utils::s3::create_s3_client;
+use crate::utils::scanner::create_file_scanner;
 use env_logger::Env;
+use std::time::Instant;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+    // Validate all required settings up front and report every problem at once
+    let config = Config::from_env().unwrap_or_else(|errors| {
+        error!("Invalid configuration:\n  - {}", errors.join("\n  - "));
+        std::process::exit(1);
+    });
+    utils::date_format::init(config.date_format);
+
     // Initialize S3 client
-    let s3_client = create_s3_client().await;
+    let s3_client = create_s3_client(config.aws_region.clone()).await;
 
-    // Validate JWT secret
-    let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    if jwt_secret.is_empty() {
-        panic!("JWT_SECRET cannot be empty");
-    }
+    // Initialize the upload scanner
+    let file_scanner = create_file_scanner();
 
     // Initialize the database pool
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = PgPool::connect(&database_url).await.expect("Failed to connect to the database");
+    let pool = db::create_pool(config.database.clone(), config.db_min_connections).await;
+
+    // Prime the pool so the first requests don't wait on new connections
+    if config::env_flag("DB_WARMUP") {
+        let connections = config.db_min_connections.max(1);
+        let started = Instant::now();
+        match db::warm_up(&pool, connections).await {
+            Ok(()) => info!("Warmed up {} database connections in {:?}", connections, started.elapsed()),
+            Err(err) => error!("Database pool warm-up failed: {:?}", err),
+        }
+    }
+
+    let bind_address = config.bind_address;
+    // Headers get the same timeout as bodies
+    let request_timeout = config.request_timeout;
+    let mut state = AppState::new(config, pool, s3_client, file_scanner);
+
+    // With `AUTO_MIGRATE`, migrations run in the background while the server starts;
+    // data endpoints answer 503 until they finish, and stay that way if they fail
+    if config::env_flag("AUTO_MIGRATE") {
+        state.readiness = web::Data::new(Readiness::new(false));
+        let pool = state.pool.clone();
+        let readiness = state.readiness.clone();
+        actix_web::rt::spawn(async move {
+            let started = Instant::now();
+            match sqlx::migrate!("./src/db/migrations").run(&pool).await {
+                Ok(()) => {
+                    info!("Migrations completed in {:?}", started.elapsed());
+                    readiness.mark_ready();
+                }
+                Err(err) => error!("Migrations failed: {:?}", err),
+            }
+        });
+    }
 
-    info!("Starting server at 127.0.0.1:8080");
+    info!("Starting server at {}", bind_address);
 
     // Start the HTTP server
-    HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(pool.clone()))
-            .app_data(web::Data::new(s3_client.clone())) // Add S3 client to app data
-            .service(
-                web::resource("/v1/auth")
-                    .route(web::post().to(handlers::auth::auth_handler)),
-            )
-            .service(
-                web::resource("/v1/user")
-                    .route(web::get().to(handlers::user::get_user_profile))
-                    .route(web::patch().to(handlers::user::update_user_profile)),
-            )
-            .service(
-                web::resource("/v1/file")
-                    .route(web::post().to(handlers::file::upload_file)),
-            )
-            .service(
-                web::resource("/v1/employee")
-                    .route(web::post().to(handlers::employee::create_employee))
-                    .route(web::get().to(handlers::employee::get_employees))
-                    .route(web::patch().to(handlers::employee::update_employee))
-                    .route(web::delete().to(handlers::employee::delete_employee)),
-            )
-            .service(
-                web::resource("/v1/department")
-                    .route(web::post().to(handlers::department::create_department))
-                    .route(web::get().to(handlers::department::get_departments))
-                    .route(web::patch().to(handlers::department::update_department))
-                    .route(web::delete().to(handlers::department::delete_department)),
-            )
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
-}
\ No newline at end of file
+    HttpServer::new(move || app::build_app(&state))
+        .client_request_timeout(request_timeout)
+        .bind(bind_address)?
+        .run()
+        .await
+}
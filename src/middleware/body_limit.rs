@@ -0,0 +1,165 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use futures_util::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// App-wide request body limits, applied before any extractor runs.
+pub struct BodyLimits {
+    max_size: usize,
+    read_timeout: Duration,
+    /// Larger limits for specific route patterns, such as the admin import
+    route_max_sizes: HashMap<&'static str, usize>,
+}
+
+impl BodyLimits {
+    pub fn new(max_size: usize, read_timeout: Duration) -> Self {
+        BodyLimits {
+            max_size,
+            read_timeout,
+            route_max_sizes: HashMap::new(),
+        }
+    }
+
+    pub fn with_route_max_size(mut self, pattern: &'static str, max_size: usize) -> Self {
+        self.route_max_sizes.insert(pattern, max_size);
+        self
+    }
+
+    fn max_size_for(&self, req: &ServiceRequest) -> usize {
+        req.request()
+            .match_pattern()
+            .and_then(|pattern| self.route_max_sizes.get(pattern.as_str()).copied())
+            .unwrap_or(self.max_size)
+    }
+}
+
+/// Reads the whole body within the read timeout, returning `408 Request Timeout` for
+/// slow clients and `413 Payload Too Large` for oversized bodies, then hands the
+/// buffered body on to the handler.
+pub async fn limit_body(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(limits) = req.app_data::<web::Data<BodyLimits>>().cloned() else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    let max_size = limits.max_size_for(&req);
+
+    // Reject declared oversized bodies without reading them
+    let declared_size = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared_size.is_some_and(|size| size > max_size) {
+        return Ok(reject(req, HttpResponse::PayloadTooLarge(), "Request body too large"));
+    }
+
+    let mut payload = req.take_payload();
+    let read = tokio::time::timeout(limits.read_timeout, async {
+        let mut body = web::BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(|_| BodyError::Invalid)?;
+            if body.len() + chunk.len() > max_size {
+                return Err(BodyError::TooLarge);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.freeze())
+    })
+    .await;
+
+    match read {
+        Ok(Ok(body)) => {
+            req.set_payload(Payload::from(body));
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        Ok(Err(BodyError::TooLarge)) => Ok(reject(req, HttpResponse::PayloadTooLarge(), "Request body too large")),
+        Ok(Err(BodyError::Invalid)) => Ok(reject(req, HttpResponse::BadRequest(), "Invalid request body")),
+        Err(_) => Ok(reject(req, HttpResponse::RequestTimeout(), "Request body not received in time")),
+    }
+}
+
+enum BodyError {
+    TooLarge,
+    Invalid,
+}
+
+fn reject<B>(
+    req: ServiceRequest,
+    mut response: actix_web::HttpResponseBuilder,
+    message: &str,
+) -> ServiceResponse<actix_web::body::EitherBody<B>> {
+    // The connection can't be reused once the body was only partially read
+    let response = response
+        .insert_header((header::CONNECTION, "close"))
+        .json(json!({ "error": message }));
+    req.into_response(response).map_into_right_body()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::{BoxedPayloadStream, Request};
+    use actix_web::dev::Payload;
+    use actix_web::error::PayloadError;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::web::Bytes;
+    use futures_util::{stream, StreamExt};
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use crate::app::build_app;
+    use crate::test_support::{state_with, test_config, test_pool};
+
+    /// A body without a declared length, sent as `chunks` after `delay`
+    fn streamed(chunks: Vec<&'static [u8]>, delay: Duration) -> Request {
+        let body: BoxedPayloadStream = Box::pin(stream::iter(chunks).then(move |chunk| async move {
+            tokio::time::sleep(delay).await;
+            Ok::<_, PayloadError>(Bytes::from_static(chunk))
+        }));
+        let (req, _) = TestRequest::post()
+            .uri("/v1/employee")
+            .to_request()
+            .replace_payload(Payload::from(body));
+        req
+    }
+
+    #[actix_web::test]
+    async fn rejects_a_body_that_arrives_too_slowly() {
+        let mut config = test_config();
+        config.request_timeout = Duration::from_millis(50);
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let res = test::call_service(&app, streamed(vec![b"{}"], Duration::from_millis(500))).await;
+
+        assert_eq!(res.status(), StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(res.headers().get("connection").unwrap(), "close");
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body, json!({ "error": "Request body not received in time" }));
+    }
+
+    #[actix_web::test]
+    async fn rejects_an_oversized_body() {
+        let mut config = test_config();
+        config.max_request_body_size = 16;
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        // Declared up front, and discovered while streaming
+        let declared = TestRequest::post().uri("/v1/employee").set_payload(vec![b'x'; 32]).to_request();
+        let streamed = streamed(vec![b"0123456789", b"0123456789"], Duration::ZERO);
+
+        for req in [declared, streamed] {
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body, json!({ "error": "Request body too large" }));
+        }
+    }
+}
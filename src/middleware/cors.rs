@@ -0,0 +1,148 @@
+use actix_cors::Cors;
+use actix_web::http::header::HeaderValue;
+use std::env;
+
+/// Origins allowed by `CORS_ALLOWED_ORIGINS`, a comma-separated list such as
+/// `https://app.com,https://*.app.com`. A leading `*.` matches any subdomain,
+/// and patterns without a scheme match both http and https.
+#[derive(Clone, Default)]
+pub struct AllowedOrigins {
+    patterns: Vec<OriginPattern>,
+}
+
+#[derive(Clone)]
+struct OriginPattern {
+    scheme: Option<String>,
+    host: HostPattern,
+}
+
+#[derive(Clone)]
+enum HostPattern {
+    Exact(String),
+    /// Stored with its leading dot, e.g. `.app.com`
+    Subdomain(String),
+}
+
+impl AllowedOrigins {
+    pub fn from_env() -> Self {
+        let patterns = env::var("CORS_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(OriginPattern::parse)
+            .collect();
+
+        AllowedOrigins { patterns }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Checks an `Origin` header value such as `https://tenant1.app.com:8443`.
+    pub fn matches(&self, origin: &str) -> bool {
+        let Ok(url) = url::Url::parse(origin) else {
+            return false;
+        };
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let authority = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        self.patterns.iter().any(|pattern| pattern.matches(url.scheme(), &authority))
+    }
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> Self {
+        let pattern = pattern.to_lowercase();
+        let (scheme, host) = match pattern.split_once("://") {
+            Some((scheme, host)) => (Some(scheme.to_string()), host.trim_end_matches('/')),
+            None => (None, pattern.trim_end_matches('/')),
+        };
+
+        let host = match host.strip_prefix('*') {
+            Some(suffix) if suffix.starts_with('.') => HostPattern::Subdomain(suffix.to_string()),
+            _ => HostPattern::Exact(host.to_string()),
+        };
+
+        OriginPattern { scheme, host }
+    }
+
+    fn matches(&self, scheme: &str, authority: &str) -> bool {
+        if self.scheme.as_deref().is_some_and(|expected| expected != scheme) {
+            return false;
+        }
+
+        match &self.host {
+            HostPattern::Exact(host) => host == authority,
+            // The suffix keeps its dot, so `*.app.com` matches neither `app.com` nor `evilapp.com`
+            HostPattern::Subdomain(suffix) => authority.len() > suffix.len() && authority.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+/// Builds the CORS middleware, reflecting the request's exact origin when it is allowed.
+/// Preflight responses are cached by browsers for `max_age` seconds (`CORS_MAX_AGE`).
+pub fn build_cors(allowed_origins: AllowedOrigins, max_age: usize) -> Cors {
+    Cors::default()
+        .allowed_origin_fn(move |origin: &HeaderValue, _| {
+            origin.to_str().is_ok_and(|origin| allowed_origins.matches(origin))
+        })
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(max_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origins(patterns: &[&str]) -> AllowedOrigins {
+        AllowedOrigins { patterns: patterns.iter().map(|pattern| OriginPattern::parse(pattern)).collect() }
+    }
+
+    #[test]
+    fn wildcard_matches_subdomains_only() {
+        let allowed = origins(&["https://*.app.com"]);
+        assert!(allowed.matches("https://tenant1.app.com"));
+        assert!(allowed.matches("https://a.b.app.com"));
+        assert!(!allowed.matches("https://app.com"));
+        assert!(!allowed.matches("https://evilapp.com"));
+        assert!(!allowed.matches("http://tenant1.app.com"));
+    }
+
+    #[test]
+    fn pattern_without_scheme_matches_http_and_https() {
+        let allowed = origins(&["app.com"]);
+        assert!(allowed.matches("https://app.com"));
+        assert!(allowed.matches("http://app.com"));
+        assert!(!allowed.matches("https://www.app.com"));
+    }
+
+    #[test]
+    fn ports_must_match() {
+        let allowed = origins(&["https://app.com:8443"]);
+        assert!(allowed.matches("https://app.com:8443"));
+        assert!(!allowed.matches("https://app.com"));
+        // The default port is dropped from the origin, so it matches a pattern without one
+        assert!(origins(&["https://app.com"]).matches("https://app.com:443"));
+    }
+
+    #[test]
+    fn patterns_are_case_insensitive_and_ignore_trailing_slashes() {
+        assert!(origins(&["HTTPS://App.com/"]).matches("https://app.com"));
+    }
+
+    #[test]
+    fn rejects_unparseable_origins() {
+        let allowed = origins(&["app.com"]);
+        assert!(!allowed.matches("null"));
+        assert!(!allowed.matches(""));
+        assert!(!AllowedOrigins::default().is_enabled());
+    }
+}
@@ -0,0 +1,126 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web, Error,
+};
+use crate::config::{Config, ErrorFormat};
+use crate::errors::{self, AppError, GENERIC_SERVER_ERROR};
+
+/// Renders `AppError` responses in the configured `ERROR_FORMAT` and, with
+/// `PRODUCTION_ERRORS`, replaces the body of every server error with a generic message.
+/// Sits directly around the routes, where handler and extractor errors are still attached
+/// to their responses.
+pub async fn render_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let (format, production_errors) = req
+        .app_data::<web::Data<Config>>()
+        .map(|config| (config.error_format, config.production_errors))
+        .unwrap_or((ErrorFormat::Simple, false));
+
+    let res = next.call(req).await?;
+    let status = res.status();
+    let Some(err) = res.response().error() else {
+        return Ok(res.map_into_left_body());
+    };
+    let app_error = err.as_error::<AppError>();
+
+    // Server error messages can carry SQL or connection details; keep those in the logs
+    let message = if status.is_server_error() && production_errors {
+        if let Some(app_error) = app_error {
+            log::error!("{}", app_error);
+        }
+        GENERIC_SERVER_ERROR.to_string()
+    } else {
+        match app_error {
+            Some(app_error) if format != ErrorFormat::Simple => app_error.message().to_string(),
+            _ => return Ok(res.map_into_left_body()),
+        }
+    };
+
+    Ok(res.into_response(errors::error_body(status, &message, format)).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+    use crate::app::build_app;
+    use crate::config::ErrorFormat;
+    use crate::errors::GENERIC_SERVER_ERROR;
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{authed, state_with, test_config, test_pool, token_for, unreachable_pool};
+
+    /// A `400 Invalid department ID` from `AppError`, answered before any query runs
+    async fn invalid_department_error(format: ErrorFormat) -> (StatusCode, String, Value) {
+        let mut config = test_config();
+        config.error_format = format;
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let uri = "/v1/department/not-a-uuid/stats";
+        let req = authed(TestRequest::get().uri(uri), &token_for(Uuid::new_v4(), ROLE_MEMBER)).to_request();
+        let res = test::call_service(&app, req).await;
+
+        let status = res.status();
+        let content_type = res.headers().get(header::CONTENT_TYPE).unwrap().to_str().unwrap().to_string();
+        (status, content_type, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn renders_the_simple_format_by_default() {
+        let (status, content_type, body) = invalid_department_error(ErrorFormat::Simple).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/json");
+        assert_eq!(body, json!({ "error": "Invalid department ID" }));
+    }
+
+    #[actix_web::test]
+    async fn renders_problem_details_when_configured() {
+        let (status, content_type, body) = invalid_department_error(ErrorFormat::Problem).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(content_type, "application/problem+json");
+        assert_eq!(body, json!({
+            "type": "about:blank",
+            "title": "Bad Request",
+            "status": 400,
+            "detail": "Invalid department ID",
+        }));
+    }
+
+    /// `department_stats` for an existing-looking ID, whose first query fails
+    async fn database_error(production_errors: bool) -> (StatusCode, Value) {
+        let mut config = test_config();
+        config.production_errors = production_errors;
+        let app = test::init_service(build_app(&state_with(config, unreachable_pool()))).await;
+
+        let uri = format!("/v1/department/{}/stats", Uuid::new_v4());
+        let req = authed(TestRequest::get().uri(&uri), &token_for(Uuid::new_v4(), ROLE_MEMBER)).to_request();
+        let res = test::call_service(&app, req).await;
+
+        (res.status(), test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn shows_database_error_detail_outside_production() {
+        let (status, body) = database_error(false).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        let message = body["error"].as_str().unwrap();
+        assert!(!message.is_empty());
+        assert_ne!(message, GENERIC_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn hides_database_error_detail_in_production() {
+        let (status, body) = database_error(true).await;
+
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body, json!({ "error": GENERIC_SERVER_ERROR }));
+    }
+}
@@ -0,0 +1,103 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use serde_json::json;
+use crate::config::Config;
+
+/// Rejects requests that the TLS-terminating proxy forwarded over plain HTTP and
+/// sets `Strict-Transport-Security` on everything else. Enabled by `REQUIRE_HTTPS`.
+pub async fn require_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(config) = req.app_data::<web::Data<Config>>().cloned() else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+
+    // Proxies may append to the header, so the first hop is the client-facing one
+    let is_https = req
+        .headers()
+        .get(&config.forwarded_proto_header)
+        .and_then(|proto| proto.to_str().ok())
+        .and_then(|proto| proto.split(',').next())
+        .map(|proto| proto.trim().eq_ignore_ascii_case("https"))
+        .unwrap_or(false);
+
+    if !is_https {
+        let response = HttpResponse::BadRequest().json(json!({ "error": "HTTPS is required" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    let hsts = format!("max-age={}; includeSubDomains", config.hsts_max_age);
+    if let Ok(value) = HeaderValue::from_str(&hsts) {
+        res.headers_mut().insert(header::STRICT_TRANSPORT_SECURITY, value);
+    }
+    Ok(res.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+    use crate::app::build_app;
+    use crate::config::Config;
+    use crate::test_support::{config_from, state_with, test_pool};
+
+    fn https_config(vars: &[(&str, Option<&str>)]) -> Config {
+        let mut vars = vars.to_vec();
+        vars.push(("REQUIRE_HTTPS", Some("true")));
+        config_from(&vars).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn rejects_requests_forwarded_over_http() {
+        let app = test::init_service(build_app(&state_with(https_config(&[]), test_pool()))).await;
+
+        for proto in [None, Some("http"), Some("http, https")] {
+            let mut req = TestRequest::get().uri("/");
+            if let Some(proto) = proto {
+                req = req.insert_header(("X-Forwarded-Proto", proto));
+            }
+            let res = test::call_service(&app, req.to_request()).await;
+
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{:?}", proto);
+            assert!(!res.headers().contains_key(header::STRICT_TRANSPORT_SECURITY));
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body, json!({ "error": "HTTPS is required" }));
+        }
+    }
+
+    #[actix_web::test]
+    async fn allows_https_and_sends_hsts() {
+        let app = test::init_service(build_app(&state_with(https_config(&[]), test_pool()))).await;
+
+        let req = TestRequest::get().uri("/").insert_header(("X-Forwarded-Proto", "HTTPS")).to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+    }
+
+    #[actix_web::test]
+    async fn reads_the_configured_proto_header() {
+        let config = https_config(&[("FORWARDED_PROTO_HEADER", Some("X-Scheme")), ("HSTS_MAX_AGE", Some("60"))]);
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let req = TestRequest::get().uri("/").insert_header(("X-Forwarded-Proto", "https")).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::get().uri("/").insert_header(("X-Scheme", "https")).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(header::STRICT_TRANSPORT_SECURITY).unwrap(), "max-age=60; includeSubDomains");
+    }
+}
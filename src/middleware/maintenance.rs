@@ -0,0 +1,95 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method},
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::config::Config;
+
+/// Non-GET paths that stay available while maintenance is on: the admin toggle
+/// itself and read-only POST endpoints
+const MAINTENANCE_EXEMPT_PATHS: &[&str] = &["/v1/admin/maintenance", "/v1/auth/introspect"];
+
+/// Shared flag that rejects writes while maintenance is in progress.
+pub struct MaintenanceMode(AtomicBool);
+
+impl MaintenanceMode {
+    pub fn new(enabled: bool) -> Self {
+        MaintenanceMode(AtomicBool::new(enabled))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Returns `503 Service Unavailable` for non-read requests while maintenance is on.
+pub async fn reject_writes_during_maintenance(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let enabled = req
+        .app_data::<web::Data<MaintenanceMode>>()
+        .map(|mode| mode.is_enabled())
+        .unwrap_or(false);
+    let is_read = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+
+    if enabled && !is_read && !MAINTENANCE_EXEMPT_PATHS.contains(&req.path()) {
+        let retry_after = req
+            .app_data::<web::Data<Config>>()
+            .map(|config| config.maintenance_retry_after)
+            .unwrap_or(120);
+        let response = HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+            .json(json!({ "error": "Service is under maintenance" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use serde_json::{json, Value};
+    use crate::app::build_app;
+    use crate::test_support::{config_from, state_with, test_config, test_pool};
+
+    #[actix_web::test]
+    async fn rejects_writes_but_serves_reads() {
+        let config = config_from(&[("MAINTENANCE_MODE", Some("on")), ("MAINTENANCE_RETRY_AFTER", Some("30"))]).unwrap();
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let res = test::call_service(&app, TestRequest::post().uri("/v1/employee").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "30");
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body, json!({ "error": "Service is under maintenance" }));
+
+        let res = test::call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn applies_a_runtime_toggle() {
+        let state = state_with(test_config(), test_pool());
+        let app = test::init_service(build_app(&state)).await;
+
+        state.maintenance_mode.set(true);
+        let res = test::call_service(&app, TestRequest::post().uri("/v1/employee").to_request()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "120");
+
+        state.maintenance_mode.set(false);
+        let res = test::call_service(&app, TestRequest::post().uri("/v1/employee").to_request()).await;
+        assert_ne!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
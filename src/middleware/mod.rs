@@ -0,0 +1,7 @@
+pub mod maintenance;
+pub mod https;
+pub mod rate_limit;
+pub mod cors;
+pub mod body_limit;
+pub mod readiness;
+pub mod errors;
@@ -0,0 +1,171 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::utils;
+
+/// Callers of the auth route are not authenticated yet, so it is always keyed by IP
+const AUTH_ROUTE: &str = "/v1/auth";
+
+/// Number of tracked windows above which expired ones are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Fixed-window request counters keyed by route pattern and caller.
+pub struct RateLimiter {
+    limits: HashMap<String, u32>,
+    window: Duration,
+    counters: Mutex<HashMap<(String, String), (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Limits are requests per `window` by route pattern, from `Config::rate_limits`.
+    pub fn new(limits: HashMap<String, u32>, window: Duration) -> Self {
+        RateLimiter {
+            limits,
+            window,
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.limits.is_empty()
+    }
+
+    /// Counts a request, returning the seconds until the window resets when over the limit.
+    fn check(&self, route: &str, caller: &str) -> Result<(), u64> {
+        let Some(&limit) = self.limits.get(route) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut counters = self.counters.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if counters.len() > PRUNE_THRESHOLD {
+            counters.retain(|_, (started, _)| now.duration_since(*started) < self.window);
+        }
+
+        let (started, count) = counters
+            .entry((route.to_string(), caller.to_string()))
+            .or_insert((now, 0));
+
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *count = 0;
+        }
+
+        if *count >= limit {
+            let remaining = self.window.saturating_sub(now.duration_since(*started));
+            return Err(remaining.as_secs().max(1));
+        }
+
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Returns `429 Too Many Requests` with `Retry-After` once a caller exceeds a route's limit.
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if let Some(limiter) = req.app_data::<web::Data<RateLimiter>>() {
+        let route = req.request().match_pattern().unwrap_or_else(|| req.path().to_string());
+        let caller = caller_key(&req, &route);
+
+        if let Err(retry_after) = limiter.check(&route, &caller) {
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(json!({ "error": "Too many requests" }));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// Authenticated callers are limited per user; auth and anonymous requests per client IP.
+fn caller_key(req: &ServiceRequest, route: &str) -> String {
+    if route != AUTH_ROUTE {
+        if let Ok(claims) = utils::jwt::authenticate(req.request()) {
+            return format!("user:{}", claims.sub);
+        }
+    }
+
+    let ip = utils::client_ip::client_ip(req.request()).unwrap_or_else(|| "unknown".to_string());
+    format!("ip:{}", ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::test::{self, TestRequest};
+    use serde_json::json;
+    use std::net::SocketAddr;
+    use uuid::Uuid;
+    use crate::app::build_app;
+    use crate::models::user::ROLE_MEMBER;
+    use crate::test_support::{authed, config_from, state_with, test_pool, token_for};
+
+    const PEER: &str = "203.0.113.7:50000";
+
+    fn auth_attempt(forwarded_for: &str) -> TestRequest {
+        TestRequest::post()
+            .uri("/v1/auth")
+            .peer_addr(PEER.parse::<SocketAddr>().unwrap())
+            .insert_header(("X-Forwarded-For", forwarded_for))
+            .set_json(json!({}))
+    }
+
+    #[actix_web::test]
+    async fn limits_each_user_separately() {
+        let config = config_from(&[("RATE_LIMITS", Some(r#"{"/v1/file": 1}"#))]).unwrap();
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+        let upload = |user_id| authed(TestRequest::post().uri("/v1/file"), &token_for(user_id, ROLE_MEMBER)).to_request();
+        let (first_user, second_user) = (Uuid::new_v4(), Uuid::new_v4());
+
+        // The empty body fails in the handler; only the limiter answers 429
+        let res = test::call_service(&app, upload(first_user)).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = test::call_service(&app, upload(first_user)).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key("Retry-After"));
+
+        let res = test::call_service(&app, upload(second_user)).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn ignores_forwarded_headers_by_default() {
+        let config = config_from(&[("RATE_LIMITS", Some(r#"{"/v1/auth": 1}"#))]).unwrap();
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let res = test::call_service(&app, auth_attempt("198.51.100.1").to_request()).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = test::call_service(&app, auth_attempt("198.51.100.2").to_request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn keys_on_forwarded_headers_behind_a_trusted_proxy() {
+        let config = config_from(&[
+            ("RATE_LIMITS", Some(r#"{"/v1/auth": 1}"#)),
+            ("TRUST_PROXY_HEADERS", Some("true")),
+        ])
+        .unwrap();
+        let app = test::init_service(build_app(&state_with(config, test_pool()))).await;
+
+        let res = test::call_service(&app, auth_attempt("198.51.100.1").to_request()).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = test::call_service(&app, auth_attempt("198.51.100.2").to_request()).await;
+        assert_ne!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let res = test::call_service(&app, auth_attempt("198.51.100.1").to_request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,80 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, Error, HttpResponse,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Paths that answer before the schema is ready, so health checkers see the process is up
+const READINESS_EXEMPT_PATHS: &[&str] = &["/", "/health"];
+
+/// Shared flag that is set once startup migrations have finished.
+pub struct Readiness(AtomicBool);
+
+impl Readiness {
+    pub fn new(ready: bool) -> Self {
+        Readiness(AtomicBool::new(ready))
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    pub fn mark_ready(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Returns `503 Service Unavailable` for data endpoints until the app is ready.
+pub async fn reject_until_ready(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let ready = req
+        .app_data::<web::Data<Readiness>>()
+        .map(|readiness| readiness.is_ready())
+        .unwrap_or(true);
+
+    if !ready && !READINESS_EXEMPT_PATHS.contains(&req.path()) {
+        let response = HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, "5"))
+            .json(json!({ "error": "Service is starting up" }));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{self, TestRequest};
+    use actix_web::web;
+    use serde_json::json;
+    use super::Readiness;
+    use crate::app::build_app;
+    use crate::test_support::{state_with, test_config, test_pool};
+
+    #[actix_web::test]
+    async fn holds_data_endpoints_until_ready_while_health_stays_up() {
+        let mut state = state_with(test_config(), test_pool());
+        state.readiness = web::Data::new(Readiness::new(false));
+        let app = test::init_service(build_app(&state)).await;
+        // Answers without the database, so only readiness decides the status
+        let introspect = || TestRequest::post().uri("/v1/auth/introspect").set_json(json!({ "token": "x" })).to_request();
+        let health = || TestRequest::get().uri("/health").to_request();
+
+        let res = test::call_service(&app, introspect()).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "5");
+        assert_eq!(test::call_service(&app, health()).await.status(), StatusCode::OK);
+
+        state.readiness.mark_ready();
+
+        assert_eq!(test::call_service(&app, introspect()).await.status(), StatusCode::OK);
+        assert_eq!(test::call_service(&app, health()).await.status(), StatusCode::OK);
+    }
+}
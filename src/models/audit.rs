@@ -0,0 +1,7 @@
+//! Actions recorded in `audit_log` with `db::record_audit`.
+
+/// A user's role changed; details hold the `from` and `to` roles
+pub const ACTION_ROLE_CHANGED: &str = "role_changed";
+
+/// An admin reset a user's password; details hold `generated` and `forceChange`
+pub const ACTION_PASSWORD_RESET: &str = "password_reset";
@@ -2,10 +2,17 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+pub const STATUS_ACTIVE: &str = "active";
+pub const STATUS_ARCHIVED: &str = "archived";
+
 #[derive(sqlx::FromRow, Serialize, Deserialize, Debug)]
 pub struct Department {
     pub department_id: Uuid,
     pub name: String,
+    pub code: String,
+    pub status: String,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
     pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
     pub updated_at: DateTime<Utc>,
 }
\ No newline at end of file
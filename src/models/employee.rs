@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+
+pub const EMPLOYMENT_ACTIVE: &str = "active";
+pub const EMPLOYMENT_STATUSES: &[&str] = &[EMPLOYMENT_ACTIVE, "on_leave", "terminated"];
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Debug)]
 pub struct Employee {
@@ -10,6 +13,23 @@ pub struct Employee {
     pub employee_image_uri: Option<String>,
     pub gender: String,
     pub department_id: Uuid,
+    pub employment_status: String,
+    pub hire_date: Option<NaiveDate>,
+    /// Minor currency units; only ever serialized for admins
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub salary: Option<i64>,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
     pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
     pub updated_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+impl Employee {
+    /// Drops the salary unless the caller is allowed to see it.
+    pub fn redact_salary(mut self, visible: bool) -> Self {
+        if !visible {
+            self.salary = None;
+        }
+        self
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Full dataset dump produced by `GET /v1/admin/export` and accepted by `POST /v1/admin/import`.
+/// Timestamps always use chrono's RFC 3339 form so dumps import regardless of `DATE_FORMAT`.
+#[derive(Deserialize, Debug)]
+pub struct DataExport {
+    pub users: Vec<ExportedUser>,
+    pub departments: Vec<ExportedDepartment>,
+    pub employees: Vec<ExportedEmployee>,
+}
+
+/// Users are exported without their password hashes
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedUser {
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: Option<String>,
+    pub user_image_uri: Option<String>,
+    pub company_name: Option<String>,
+    pub company_image_uri: Option<String>,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedDepartment {
+    pub department_id: Uuid,
+    pub name: String,
+    pub code: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExportedEmployee {
+    pub employee_id: Uuid,
+    pub identity_number: String,
+    pub name: String,
+    pub employee_image_uri: Option<String>,
+    pub gender: String,
+    pub department_id: Uuid,
+    pub employment_status: String,
+    pub hire_date: Option<NaiveDate>,
+    pub salary: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
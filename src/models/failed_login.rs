@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+/// A rejected login attempt; `email` is stored masked, e.g. `jo***@example.com`
+#[derive(sqlx::FromRow, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedLogin {
+    pub failed_login_id: i64,
+    pub email: String,
+    pub source_ip: Option<String>,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
+    pub attempted_at: DateTime<Utc>,
+}
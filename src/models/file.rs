@@ -2,10 +2,19 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
 
+/// Presigned uploads start pending and become confirmed once the object is in S3
+pub const FILE_PENDING: &str = "pending";
+pub const FILE_CONFIRMED: &str = "confirmed";
+
 #[derive(sqlx::FromRow, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct File {
     pub file_id: Uuid,
+    #[serde(skip_serializing)]
     pub user_id: Uuid,
     pub uri: String,
+    pub content_type: String,
+    pub status: String,
+    #[serde(serialize_with = "crate::utils::date_format::serialize")]
     pub created_at: chrono::DateTime<Utc>,
 }
\ No newline at end of file
@@ -1,4 +1,8 @@
 pub mod user;
 pub mod employee;
 pub mod department;
-pub mod file;
\ No newline at end of file
+pub mod file;
+pub mod pagination;
+pub mod export;
+pub mod failed_login;
+pub mod audit;
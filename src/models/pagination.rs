@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+/// Opt-in `{ data, meta }` wrapper for listing endpoints.
+#[derive(Serialize, Debug)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub meta: PageMeta,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PageMeta {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new(data: Vec<T>, limit: Option<i64>, offset: Option<i64>, total: i64) -> Self {
+        Page {
+            data,
+            meta: PageMeta { limit, offset, total },
+        }
+    }
+}
@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::Utc;
 
+pub const ROLE_ADMIN: &str = "admin";
+pub const ROLE_MEMBER: &str = "member";
+pub const ROLES: &[&str] = &[ROLE_ADMIN, ROLE_MEMBER];
+
 #[derive(sqlx::FromRow, Serialize, Deserialize, Debug)]
 pub struct User {
     pub user_id: Uuid,
@@ -11,6 +15,7 @@ pub struct User {
     pub user_image_uri: Option<String>,
     pub company_name: Option<String>,
     pub company_image_uri: Option<String>,
+    pub role: String,
     pub created_at: Option<chrono::DateTime<Utc>>,
     pub updated_at: Option<chrono::DateTime<Utc>>,
 }
@@ -0,0 +1,20 @@
+use actix_web::{web, HttpRequest};
+use crate::config::Config;
+
+/// The IP address of the client, for rate limiting and the failed-login log.
+///
+/// `Forwarded` and `X-Forwarded-For` are set by whoever sends the request, so a client
+/// could rotate them to get a fresh rate-limit bucket on every call. They are only used
+/// with `TRUST_PROXY_HEADERS`, when a proxy in front of the app overwrites them; otherwise
+/// this is the address of the socket peer.
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let trust_proxy_headers = req
+        .app_data::<web::Data<Config>>()
+        .is_some_and(|config| config.trust_proxy_headers);
+
+    if trust_proxy_headers {
+        req.connection_info().realip_remote_addr().map(str::to_string)
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::sync::OnceLock;
+use crate::config::DateFormat;
+
+/// Set once at startup from `Config::date_format`; serde hooks can't reach app state.
+static FORMAT: OnceLock<DateFormat> = OnceLock::new();
+
+/// Selects the format used by every hook below. Later calls are ignored.
+pub fn init(format: DateFormat) {
+    let _ = FORMAT.set(format);
+}
+
+fn configured_format() -> DateFormat {
+    FORMAT.get().copied().unwrap_or(DateFormat::Rfc3339)
+}
+
+/// `serialize_with` hook for response timestamps.
+/// `rfc3339` (default) keeps chrono's output, `rfc3339-millis` truncates to
+/// milliseconds and `unix` emits whole seconds since the epoch.
+pub fn serialize<S: Serializer>(timestamp: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_as(configured_format(), timestamp, serializer)
+}
+
+fn serialize_as<S: Serializer>(format: DateFormat, timestamp: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match format {
+        DateFormat::Rfc3339 => timestamp.serialize(serializer),
+        DateFormat::Rfc3339Millis => {
+            serializer.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Millis, true))
+        }
+        DateFormat::Unix => serializer.serialize_i64(timestamp.timestamp()),
+    }
+}
+
+/// Same as [`serialize`], for optional timestamps; `None` serializes as `null`.
+pub fn serialize_option<S: Serializer>(timestamp: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serialize(timestamp, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Same as [`serialize`], for responses assembled with `json!`.
+pub fn to_value(timestamp: &DateTime<Utc>) -> Value {
+    serialize(timestamp, serde_json::value::Serializer).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn format(format: DateFormat) -> Value {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 123_456_789).unwrap();
+        serialize_as(format, &timestamp, serde_json::value::Serializer).unwrap()
+    }
+
+    #[test]
+    fn formats_a_fixed_timestamp() {
+        assert_eq!(format(DateFormat::Rfc3339), json!("2023-11-14T22:13:20.123456789Z"));
+        assert_eq!(format(DateFormat::Rfc3339Millis), json!("2023-11-14T22:13:20.123Z"));
+        assert_eq!(format(DateFormat::Unix), json!(1_700_000_000));
+    }
+}
@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::errors::AppError;
+
+/// `?fields=` for endpoints that take no other query parameters
+#[derive(Deserialize)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+/// Top-level fields requested via a comma-separated `fields` parameter.
+/// When the parameter is absent every field is kept.
+pub struct FieldSelection(Option<Vec<String>>);
+
+impl FieldSelection {
+    /// Returns `400 Bad Request` naming any field outside `allowed`.
+    pub fn parse(raw: Option<&str>, allowed: &[&str]) -> Result<Self, AppError> {
+        let Some(raw) = raw else {
+            return Ok(FieldSelection(None));
+        };
+
+        let fields = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        if fields.is_empty() {
+            return Err(AppError::BadRequest("fields must name at least one field".to_string()));
+        }
+
+        let unknown = fields
+            .iter()
+            .filter(|field| !allowed.contains(&field.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        if !unknown.is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "Unknown fields: {} (allowed: {})",
+                unknown.join(", "),
+                allowed.join(", ")
+            )));
+        }
+
+        Ok(FieldSelection(Some(fields)))
+    }
+
+    /// Serializes `value`, keeping only the selected keys of an object or of each object in an array.
+    pub fn apply<T: Serialize>(&self, value: &T) -> Value {
+        let value = serde_json::to_value(value).unwrap_or_default();
+        match &self.0 {
+            Some(fields) => project(value, fields),
+            None => value,
+        }
+    }
+}
+
+fn project(value: Value, fields: &[String]) -> Value {
+    match value {
+        Value::Object(mut map) => {
+            map.retain(|key, _| fields.contains(key));
+            Value::Object(map)
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(|item| project(item, fields)).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ALLOWED: &[&str] = &["id", "name", "email"];
+
+    #[test]
+    fn keeps_only_the_requested_fields() {
+        let selection = FieldSelection::parse(Some("name, id"), ALLOWED).unwrap();
+        let record = json!({ "id": 1, "name": "Ada", "email": "ada@example.com" });
+        assert_eq!(selection.apply(&record), json!({ "id": 1, "name": "Ada" }));
+        assert_eq!(selection.apply(&json!([record])), json!([{ "id": 1, "name": "Ada" }]));
+
+        let everything = FieldSelection::parse(None, ALLOWED).unwrap();
+        assert_eq!(everything.apply(&record), record);
+    }
+
+    #[test]
+    fn rejects_fields_outside_the_allowlist() {
+        let err = FieldSelection::parse(Some("name,password"), ALLOWED).err();
+        assert!(matches!(err, Some(AppError::BadRequest(msg)) if msg == "Unknown fields: password (allowed: id, name, email)"));
+
+        assert!(FieldSelection::parse(Some(" , "), ALLOWED).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use actix_web::{http::header, HttpRequest};
+use validator::{ValidationError, ValidationErrors};
+
+/// Locales with translated validation messages; anything else falls back to English
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Locale {
+    En,
+    Es,
+    Id,
+}
+
+struct Messages {
+    length_between: &'static str,
+    length_min: &'static str,
+    length_max: &'static str,
+    range_between: &'static str,
+    range_min: &'static str,
+    range_max: &'static str,
+    email: &'static str,
+    url: &'static str,
+}
+
+const EN: Messages = Messages {
+    length_between: "must be between {min} and {max} characters",
+    length_min: "must be at least {min} characters",
+    length_max: "must be at most {max} characters",
+    range_between: "must be between {min} and {max}",
+    range_min: "must be at least {min}",
+    range_max: "must be at most {max}",
+    email: "must be a valid email address",
+    url: "must be a valid URL",
+};
+
+const ES: Messages = Messages {
+    length_between: "debe tener entre {min} y {max} caracteres",
+    length_min: "debe tener al menos {min} caracteres",
+    length_max: "debe tener como máximo {max} caracteres",
+    range_between: "debe estar entre {min} y {max}",
+    range_min: "debe ser al menos {min}",
+    range_max: "debe ser como máximo {max}",
+    email: "debe ser un correo electrónico válido",
+    url: "debe ser una URL válida",
+};
+
+const ID: Messages = Messages {
+    length_between: "harus terdiri dari {min} sampai {max} karakter",
+    length_min: "minimal {min} karakter",
+    length_max: "maksimal {max} karakter",
+    range_between: "harus antara {min} dan {max}",
+    range_min: "minimal {min}",
+    range_max: "maksimal {max}",
+    email: "harus berupa alamat email yang valid",
+    url: "harus berupa URL yang valid",
+};
+
+impl Locale {
+    /// Picks the highest-weighted supported language from `Accept-Language`,
+    /// e.g. `es-ES,es;q=0.9,en;q=0.8` resolves to Spanish.
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let Some(accept_language) = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+        else {
+            return Locale::En;
+        };
+
+        let mut candidates = accept_language
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.trim().split(';');
+                let tag = parts.next()?.trim();
+                let weight = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((tag, weight))
+            })
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect::<Vec<_>>();
+
+        // Stable sort keeps header order between equal weights
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        candidates
+            .into_iter()
+            .find_map(|(tag, _)| Self::from_tag(tag))
+            .unwrap_or(Locale::En)
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split('-').next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            "id" => Some(Locale::Id),
+            _ => None,
+        }
+    }
+
+    fn messages(self) -> &'static Messages {
+        match self {
+            Locale::En => &EN,
+            Locale::Es => &ES,
+            Locale::Id => &ID,
+        }
+    }
+}
+
+/// Formats field errors as `field: message; field: message`, sorted by field name.
+/// Codes without a translation (custom validators) are reported as-is.
+pub fn format_validation_errors(err: &ValidationErrors, locale: Locale) -> String {
+    let mut fields = err.field_errors().into_iter().collect::<Vec<_>>();
+    fields.sort_by_key(|(field, _)| *field);
+
+    fields
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| translate(error, locale))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{}: {}", field, messages)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn translate(error: &ValidationError, locale: Locale) -> String {
+    let messages = locale.messages();
+    let min = error.params.get("min").map(|value| value.to_string());
+    let max = error.params.get("max").map(|value| value.to_string());
+
+    let template = match (error.code.as_ref(), &min, &max) {
+        ("length", Some(_), Some(_)) => messages.length_between,
+        ("length", Some(_), None) => messages.length_min,
+        ("length", None, Some(_)) => messages.length_max,
+        ("range", Some(_), Some(_)) => messages.range_between,
+        ("range", Some(_), None) => messages.range_min,
+        ("range", None, Some(_)) => messages.range_max,
+        ("email", _, _) => messages.email,
+        ("url", _, _) => messages.url,
+        (code, _, _) => return error.message.as_deref().unwrap_or(code).to_string(),
+    };
+
+    template
+        .replace("{min}", min.as_deref().unwrap_or_default())
+        .replace("{max}", max.as_deref().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Name {
+        #[validate(length(min = 4, max = 33))]
+        name: String,
+    }
+
+    fn locale(accept_language: &str) -> Locale {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT_LANGUAGE, accept_language))
+            .to_http_request();
+        Locale::from_request(&req)
+    }
+
+    #[test]
+    fn picks_the_highest_weighted_supported_language() {
+        assert_eq!(locale("es-ES,es;q=0.9,en;q=0.8"), Locale::Es);
+        assert_eq!(locale("fr-FR,id;q=0.5,es;q=0.7"), Locale::Es);
+        assert_eq!(locale("fr-FR"), Locale::En);
+        assert_eq!(Locale::from_request(&TestRequest::default().to_http_request()), Locale::En);
+    }
+
+    #[test]
+    fn translates_length_violations() {
+        let err = Name { name: "HR".to_string() }.validate().unwrap_err();
+        assert_eq!(format_validation_errors(&err, Locale::Es), "name: debe tener entre 4 y 33 caracteres");
+        assert_eq!(format_validation_errors(&err, Locale::En), "name: must be between 4 and 33 characters");
+    }
+}
@@ -0,0 +1,111 @@
+use image::{ImageFormat, ImageReader};
+use std::io::Cursor;
+use crate::config;
+
+/// Re-encodes an image as lossless WebP.
+pub fn convert_to_webp(data: &[u8]) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(data).map_err(|err| format!("failed to decode image: {}", err))?;
+
+    let mut output = Cursor::new(Vec::new());
+    image
+        .write_to(&mut output, ImageFormat::WebP)
+        .map_err(|err| format!("failed to encode WebP: {}", err))?;
+
+    Ok(output.into_inner())
+}
+
+/// Bounds uploaded images must fit, read from `IMAGE_MIN_WIDTH`, `IMAGE_MAX_WIDTH`,
+/// `IMAGE_MIN_HEIGHT`, `IMAGE_MAX_HEIGHT`, `IMAGE_MIN_ASPECT_RATIO` and
+/// `IMAGE_MAX_ASPECT_RATIO`. Unset bounds do not restrict anything.
+pub struct DimensionLimits {
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+    min_aspect_ratio: f64,
+    max_aspect_ratio: f64,
+}
+
+impl DimensionLimits {
+    pub fn from_env() -> Self {
+        Self {
+            min_width: config::env_or("IMAGE_MIN_WIDTH", 0),
+            max_width: config::env_or("IMAGE_MAX_WIDTH", u32::MAX),
+            min_height: config::env_or("IMAGE_MIN_HEIGHT", 0),
+            max_height: config::env_or("IMAGE_MAX_HEIGHT", u32::MAX),
+            min_aspect_ratio: config::env_or("IMAGE_MIN_ASPECT_RATIO", 0.0),
+            max_aspect_ratio: config::env_or("IMAGE_MAX_ASPECT_RATIO", f64::INFINITY),
+        }
+    }
+
+    /// Reads the image header and returns a client-facing reason when it is out of bounds.
+    /// Only the dimensions are decoded, not the pixel data.
+    pub fn check(&self, data: &[u8]) -> Result<(), String> {
+        let (width, height) = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|err| format!("Unable to read image: {}", err))?
+            .into_dimensions()
+            .map_err(|err| format!("Unable to read image dimensions: {}", err))?;
+
+        if width < self.min_width || width > self.max_width {
+            return Err(format!(
+                "Image width {}px is outside the allowed range {}-{}px",
+                width, self.min_width, self.max_width
+            ));
+        }
+        if height < self.min_height || height > self.max_height {
+            return Err(format!(
+                "Image height {}px is outside the allowed range {}-{}px",
+                height, self.min_height, self.max_height
+            ));
+        }
+
+        let aspect_ratio = width as f64 / height as f64;
+        if aspect_ratio < self.min_aspect_ratio || aspect_ratio > self.max_aspect_ratio {
+            return Err(format!(
+                "Image aspect ratio {:.2} is outside the allowed range {}-{}",
+                aspect_ratio, self.min_aspect_ratio, self.max_aspect_ratio
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn limits() -> DimensionLimits {
+        DimensionLimits {
+            min_width: 100,
+            max_width: 400,
+            min_height: 100,
+            max_height: 400,
+            min_aspect_ratio: 0.5,
+            max_aspect_ratio: 2.0,
+        }
+    }
+
+    #[test]
+    fn accepts_images_within_bounds() {
+        assert_eq!(limits().check(&png(100, 100)), Ok(()));
+        assert_eq!(limits().check(&png(400, 200)), Ok(()));
+    }
+
+    #[test]
+    fn rejects_images_outside_bounds() {
+        assert_eq!(limits().check(&png(401, 300)), Err("Image width 401px is outside the allowed range 100-400px".to_string()));
+        assert_eq!(limits().check(&png(200, 99)), Err("Image height 99px is outside the allowed range 100-400px".to_string()));
+        assert_eq!(limits().check(&png(400, 100)), Err("Image aspect ratio 4.00 is outside the allowed range 0.5-2".to_string()));
+    }
+}
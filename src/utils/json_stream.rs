@@ -0,0 +1,75 @@
+use actix_web::web;
+use futures_util::{Stream, TryStreamExt};
+use log::error;
+use serde::Serialize;
+use std::future::Future;
+use tokio::sync::mpsc;
+
+pub type JsonChunk = Result<web::Bytes, std::io::Error>;
+
+/// Writes pieces of a JSON document to a streaming response body.
+/// Every write fails once the client has disconnected, ending the producer early.
+pub struct JsonStreamWriter {
+    tx: mpsc::Sender<JsonChunk>,
+}
+
+impl JsonStreamWriter {
+    pub async fn write_raw(&self, chunk: &[u8]) -> Result<(), String> {
+        self.tx
+            .send(Ok(web::Bytes::copy_from_slice(chunk)))
+            .await
+            .map_err(|_| "client disconnected".to_string())
+    }
+
+    /// Writes `"name":<value>`
+    pub async fn write_field<T: Serialize>(&self, name: &str, value: &T) -> Result<(), String> {
+        let mut chunk = serde_json::to_vec(name).map_err(|e| e.to_string())?;
+        chunk.push(b':');
+        serde_json::to_writer(&mut chunk, value).map_err(|e| e.to_string())?;
+        self.write_raw(&chunk).await
+    }
+
+    /// Writes `"name":[...]`, serializing rows one at a time as they arrive from the database.
+    pub async fn write_array_field<T, S>(&self, name: &str, mut rows: S) -> Result<(), String>
+    where
+        T: Serialize,
+        S: Stream<Item = Result<T, sqlx::Error>> + Unpin,
+    {
+        let mut chunk = serde_json::to_vec(name).map_err(|e| e.to_string())?;
+        chunk.extend_from_slice(b":[");
+        self.write_raw(&chunk).await?;
+
+        let mut first = true;
+        while let Some(row) = rows.try_next().await.map_err(|e| format!("failed to read {}: {:?}", name, e))? {
+            let mut chunk = if first { Vec::new() } else { b",".to_vec() };
+            serde_json::to_writer(&mut chunk, &row).map_err(|e| e.to_string())?;
+            self.write_raw(&chunk).await?;
+            first = false;
+        }
+
+        self.write_raw(b"]").await
+    }
+}
+
+/// Runs `produce` in the background and returns a body that streams what it writes.
+/// If the producer fails the body ends with an error, aborting the response so clients
+/// don't mistake a truncated document for a complete one.
+pub fn spawn_json_stream<F, Fut>(label: &'static str, produce: F) -> impl Stream<Item = JsonChunk>
+where
+    F: FnOnce(JsonStreamWriter) -> Fut,
+    Fut: Future<Output = Result<(), String>> + 'static,
+{
+    let (tx, rx) = mpsc::channel::<JsonChunk>(16);
+    let task = produce(JsonStreamWriter { tx: tx.clone() });
+
+    actix_web::rt::spawn(async move {
+        if let Err(err) = task.await {
+            error!("{} failed: {}", label, err);
+            let _ = tx.send(Err(std::io::Error::other(err))).await;
+        }
+    });
+
+    futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    })
+}
@@ -1,14 +1,42 @@
 use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
 use serde::{Deserialize, Serialize};
 use std::env;
+use actix_web::HttpRequest;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::config;
+use crate::errors::AppError;
+use crate::models::user::ROLE_ADMIN;
+
+/// 401 message for a missing, empty or malformed token, shared by every endpoint
+pub const MISSING_TOKEN_MESSAGE: &str = "Missing or malformed token";
+/// 401 message for a token that fails signature or expiry checks
+pub const INVALID_TOKEN_MESSAGE: &str = "Invalid or expired token";
+
+/// Cookie used for the token in `AUTH_COOKIE_MODE` when `AUTH_COOKIE_NAME` is unset
+pub const DEFAULT_AUTH_COOKIE_NAME: &str = "gogomanager_token";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID (UUID)
     pub exp: usize,  // Expiration timestamp
+    #[serde(default)]
+    pub role: String, // Tokens issued before roles existed decode as no role
 }
 
-pub fn generate_token(user_id: &str) -> Result<String, jsonwebtoken::errors::Error> {
+impl Claims {
+    pub fn is_admin(&self) -> bool {
+        self.role == ROLE_ADMIN
+    }
+
+    /// The user ID carried in `sub`; a subject that isn't a UUID is a 401.
+    pub fn user_id(&self) -> Result<Uuid, AppError> {
+        Uuid::parse_str(&self.sub)
+            .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))
+    }
+}
+
+pub fn generate_token(user_id: &str, role: &str) -> Result<String, jsonwebtoken::errors::Error> {
     let expiration = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::days(7))
         .expect("Invalid timestamp")
@@ -17,6 +45,7 @@ pub fn generate_token(user_id: &str) -> Result<String, jsonwebtoken::errors::Err
     let claims = Claims {
         sub: user_id.to_string(), // Use user_id instead of email
         exp: expiration,
+        role: role.to_string(),
     };
 
     encode(
@@ -33,4 +62,114 @@ pub fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error
         &Validation::new(jsonwebtoken::Algorithm::HS256),
     )
     .map(|data| data.claims)
-}
\ No newline at end of file
+}
+
+/// Name of the cookie carrying the token: `AUTH_COOKIE_NAME`, or the default name
+/// when `AUTH_COOKIE_MODE` is on. `None` when tokens are only read from the header.
+pub fn auth_cookie_name() -> Option<String> {
+    env::var("AUTH_COOKIE_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| config::env_flag("AUTH_COOKIE_MODE").then(|| DEFAULT_AUTH_COOKIE_NAME.to_string()))
+}
+
+/// Returns the raw token from the `Authorization` header, falling back to the
+/// auth cookie when the header is absent. A header that is not exactly
+/// `Bearer <token>` and an empty token both count as no token.
+pub fn extract_token(req: &HttpRequest) -> Option<String> {
+    if let Some(auth) = req.headers().get("Authorization") {
+        let mut parts = auth.to_str().ok()?.split_whitespace();
+        return match (parts.next(), parts.next(), parts.next()) {
+            (Some(scheme), Some(token), None) if scheme.eq_ignore_ascii_case("Bearer") => Some(token.to_string()),
+            _ => None,
+        };
+    }
+
+    let cookie_name = auth_cookie_name()?;
+    req.cookie(&cookie_name)
+        .map(|cookie| cookie.value().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// Extracts the token from the request and validates it.
+pub fn authenticate(req: &HttpRequest) -> Result<Claims, AppError> {
+    let token = extract_token(req)
+        .ok_or_else(|| AppError::Unauthorized(MISSING_TOKEN_MESSAGE.to_string()))?;
+
+    validate_token(&token).map_err(|_| AppError::Unauthorized(INVALID_TOKEN_MESSAGE.to_string()))
+}
+
+/// Authenticates the request and requires the `admin` role. The role is read from `users`
+/// rather than the token, so a demotion takes effect before the caller's token expires.
+pub async fn require_admin(req: &HttpRequest, pool: &PgPool) -> Result<Claims, AppError> {
+    let mut claims = authenticate(req)?;
+    let user_id = claims.user_id()?;
+
+    let role = sqlx::query_scalar!("SELECT role FROM users WHERE user_id = $1", user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    match role {
+        Some(role) if role == ROLE_ADMIN => {
+            claims.role = role;
+            Ok(claims)
+        }
+        _ => Err(AppError::Forbidden("Admin role required".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::cookie::Cookie;
+    use actix_web::test::TestRequest;
+    use crate::test_support::set_env_blocking;
+
+    fn claims(sub: &str) -> Claims {
+        Claims { sub: sub.to_string(), exp: 0, role: String::new() }
+    }
+
+    #[test]
+    fn user_id_parses_a_uuid_subject() {
+        let id = Uuid::new_v4();
+        assert_eq!(claims(&id.to_string()).user_id().unwrap(), id);
+    }
+
+    #[test]
+    fn user_id_rejects_a_non_uuid_subject() {
+        for sub in ["", "user@example.com", "1234"] {
+            assert!(matches!(claims(sub).user_id(), Err(AppError::Unauthorized(_))), "{:?}", sub);
+        }
+    }
+
+    #[test]
+    fn extract_token_reads_the_bearer_header() {
+        let _env = set_env_blocking(&[("AUTH_COOKIE_NAME", None), ("AUTH_COOKIE_MODE", None)]);
+        let req = TestRequest::default().insert_header(("Authorization", "Bearer header-token")).to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("header-token"));
+    }
+
+    #[test]
+    fn extract_token_falls_back_to_the_cookie() {
+        let _env = set_env_blocking(&[("AUTH_COOKIE_NAME", Some("session")), ("AUTH_COOKIE_MODE", None)]);
+        let req = TestRequest::default().cookie(Cookie::new("session", "cookie-token")).to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("cookie-token"));
+    }
+
+    #[test]
+    fn extract_token_prefers_the_header_over_the_cookie() {
+        let _env = set_env_blocking(&[("AUTH_COOKIE_NAME", Some("session")), ("AUTH_COOKIE_MODE", None)]);
+        let req = TestRequest::default()
+            .insert_header(("Authorization", "Bearer header-token"))
+            .cookie(Cookie::new("session", "cookie-token"))
+            .to_http_request();
+        assert_eq!(extract_token(&req).as_deref(), Some("header-token"));
+    }
+
+    #[test]
+    fn extract_token_ignores_the_cookie_when_no_name_is_configured() {
+        let _env = set_env_blocking(&[("AUTH_COOKIE_NAME", None), ("AUTH_COOKIE_MODE", None)]);
+        let req = TestRequest::default().cookie(Cookie::new(DEFAULT_AUTH_COOKIE_NAME, "cookie-token")).to_http_request();
+        assert_eq!(extract_token(&req), None);
+    }
+}
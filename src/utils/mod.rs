@@ -1,3 +1,15 @@
 pub mod jwt;
 pub mod validation;
-pub mod s3;
\ No newline at end of file
+pub mod s3;
+pub mod scanner;
+pub mod date_format;
+pub mod slug;
+pub mod fields;
+pub mod patch;
+pub mod i18n;
+pub mod json_stream;
+pub mod upload_limiter;
+pub mod image_processing;
+pub mod response;
+pub mod text;
+pub mod client_ip;
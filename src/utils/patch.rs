@@ -0,0 +1,88 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use crate::config;
+
+/// Deserializes a PATCH field into three states: absent (`None`), explicit `null`
+/// (`Some(None)`) and a value (`Some(Some(value))`). Pair it with `#[serde(default)]`
+/// so absent fields fall back to `None`.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Serializes the updated record and, when `INCLUDE_CHANGED_FIELDS` is on, adds a
+/// `changed` object holding only the fields whose values differ from `before`.
+/// Timestamps bumped by the write itself are not reported.
+pub fn with_changes<T: Serialize>(before: &T, after: &T) -> Value {
+    let mut after = serde_json::to_value(after).unwrap_or(Value::Null);
+    if !config::env_flag("INCLUDE_CHANGED_FIELDS") {
+        return after;
+    }
+
+    let before = serde_json::to_value(before).unwrap_or(Value::Null);
+    if let (Value::Object(before), Value::Object(after)) = (&before, &mut after) {
+        let changed: Map<String, Value> = after
+            .iter()
+            .filter(|(field, _)| !matches!(field.as_str(), "updated_at" | "updatedAt"))
+            .filter(|(field, value)| before.get(*field) != Some(*value))
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect();
+        after.insert("changed".to_string(), Value::Object(changed));
+    }
+
+    after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "double_option")]
+        name: Option<Option<String>>,
+    }
+
+    fn patch(body: Value) -> Patch {
+        serde_json::from_value(body).expect("valid patch body")
+    }
+
+    #[test]
+    fn double_option_distinguishes_absent_null_and_value() {
+        assert_eq!(patch(json!({})).name, None);
+        assert_eq!(patch(json!({ "name": null })).name, Some(None));
+        assert_eq!(patch(json!({ "name": "Ops" })).name, Some(Some("Ops".to_string())));
+    }
+
+    #[test]
+    fn double_option_rejects_the_wrong_type() {
+        assert!(serde_json::from_value::<Patch>(json!({ "name": 1 })).is_err());
+    }
+
+    fn changes_with_flag(flag: Option<&str>, before: &Value, after: &Value) -> Value {
+        let _env = crate::test_support::set_env_blocking(&[("INCLUDE_CHANGED_FIELDS", flag)]);
+        with_changes(before, after)
+    }
+
+    #[test]
+    fn with_changes_reports_changed_fields_except_updated_at() {
+        let before = json!({ "name": "Ops", "slug": "ops", "updatedAt": "2025-01-01" });
+        let after = json!({ "name": "Operations", "slug": "ops", "updatedAt": "2025-01-02" });
+
+        let body = changes_with_flag(Some("true"), &before, &after);
+        assert_eq!(body["name"], "Operations");
+        assert_eq!(body["changed"], json!({ "name": "Operations" }));
+    }
+
+    #[test]
+    fn with_changes_returns_the_record_when_off() {
+        let before = json!({ "name": "Ops" });
+        let after = json!({ "name": "Operations" });
+
+        assert_eq!(changes_with_flag(None, &before, &after), after);
+    }
+}
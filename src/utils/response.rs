@@ -0,0 +1,72 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use serde_json::json;
+use std::env;
+use crate::config;
+
+/// Response for a successful delete: `{ "message": ... }` by default, or an empty `204`
+/// when `DELETE_RESPONSE_MODE=no_content`.
+pub fn deleted(message: &str) -> HttpResponse {
+    let no_content = env::var("DELETE_RESPONSE_MODE")
+        .map(|mode| mode.trim().eq_ignore_ascii_case("no_content"))
+        .unwrap_or(false);
+
+    if no_content {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::Ok().json(json!({ "message": message }))
+    }
+}
+
+/// An error about a single input field. The body is the plain `message` by default; with
+/// `STRUCTURED_FIELD_ERRORS` it is `{ "fields": { field: [reason] } }` so forms can show it inline.
+pub fn field_error(status: StatusCode, field: &str, reason: &str, message: &str) -> actix_web::Error {
+    field_errors(status, &[(field, reason.to_string())], message)
+}
+
+/// Like `field_error`, but reports several fields in one response.
+pub fn field_errors(status: StatusCode, errors: &[(&str, String)], message: &str) -> actix_web::Error {
+    let response = if config::env_flag("STRUCTURED_FIELD_ERRORS") {
+        let mut fields = serde_json::Map::new();
+        for (field, reason) in errors {
+            if let Some(reasons) = fields.entry(field.to_string()).or_insert_with(|| json!([])).as_array_mut() {
+                reasons.push(json!(reason));
+            }
+        }
+        HttpResponse::build(status).json(json!({ "fields": fields }))
+    } else {
+        HttpResponse::build(status).body(message.to_string())
+    };
+
+    actix_web::error::InternalError::from_response(message.to_string(), response).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body;
+    use crate::test_support::set_env;
+
+    async fn delete_response(mode: Option<&str>) -> (StatusCode, Vec<u8>) {
+        let _env = set_env(&[("DELETE_RESPONSE_MODE", mode)]).await;
+        let resp = deleted("Employee deleted successfully");
+        (resp.status(), body::to_bytes(resp.into_body()).await.unwrap().to_vec())
+    }
+
+    #[actix_web::test]
+    async fn deletes_return_a_message_by_default() {
+        for mode in [None, Some("message")] {
+            let (status, body) = delete_response(mode).await;
+            assert_eq!(status, StatusCode::OK);
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, json!({ "message": "Employee deleted successfully" }));
+        }
+    }
+
+    #[actix_web::test]
+    async fn deletes_return_no_content_when_configured() {
+        let (status, body) = delete_response(Some("no_content")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(body.is_empty());
+    }
+}
@@ -1,14 +1,127 @@
 use aws_sdk_s3::Client as S3Client;
-use aws_config::ConfigLoader;
+use aws_sdk_s3::config::ProvideCredentials;
+use aws_config::{ConfigLoader, SdkConfig};
+use aws_config::meta::region::{ProvideRegion, RegionProviderChain};
 use aws_types::region::Region;
 use aws_config::BehaviorVersion; // Import BehaviorVersion
+use log::{info, warn};
+use crate::config;
+
+pub async fn create_s3_client(aws_region: Option<String>) -> S3Client {
+    let region = resolve_region(
+        aws_region,
+        aws_config::default_provider::region::default_provider(),
+        std::env::var("S3_DEFAULT_REGION").ok(),
+    )
+    .await;
 
-pub async fn create_s3_client() -> S3Client {
     let aws_config = ConfigLoader::default()
-        .region(std::env::var("AWS_REGION").ok().map(Region::new))
+        .region(region)
         .behavior_version(BehaviorVersion::latest()) // Set behavior version here
         .load()
         .await;
 
+    // Without a region every S3 call fails with an opaque SDK error, so surface it now
+    if aws_config.region().is_none() {
+        let message = "S3 region could not be resolved; set AWS_REGION or S3_DEFAULT_REGION";
+        if config::env_flag("S3_STRICT_STARTUP") {
+            panic!("{}", message);
+        }
+        warn!("{}; uploads will fail", message);
+    }
+
+    if config::env_flag("S3_DIAGNOSTICS") {
+        log_s3_diagnostics(&aws_config).await;
+    }
+
     S3Client::new(&aws_config)
-}
\ No newline at end of file
+}
+
+/// `AWS_REGION` wins, then the SDK's default chain, then `S3_DEFAULT_REGION`.
+async fn resolve_region(
+    aws_region: Option<String>,
+    default_chain: impl ProvideRegion + 'static,
+    fallback: Option<String>,
+) -> Option<Region> {
+    RegionProviderChain::first_try(aws_region.map(Region::new))
+        .or_else(default_chain)
+        .or_else(fallback.map(Region::new))
+        .region()
+        .await
+}
+
+/// Resolves credentials once at startup so a missing or broken credential source is
+/// reported immediately instead of surfacing as a failed upload.
+async fn log_s3_diagnostics(aws_config: &SdkConfig) {
+    if let Some(region) = aws_config.region() {
+        info!("S3 region: {}", region);
+    }
+
+    match credentials_source(aws_config).await {
+        Ok(source) => info!("S3 credentials resolved from {}", source),
+        Err(message) => warn!("{}; uploads will fail", message),
+    }
+}
+
+/// Names the provider that supplied credentials, or explains why none resolved.
+async fn credentials_source(aws_config: &SdkConfig) -> Result<&'static str, String> {
+    let provider = aws_config
+        .credentials_provider()
+        .ok_or_else(|| "No S3 credentials provider is configured".to_string())?;
+
+    provider
+        .provide_credentials()
+        .await
+        .map(|credentials| credentials.provider_name())
+        .map_err(|err| format!("S3 credentials could not be resolved: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_credential_types::provider::error::CredentialsError;
+    use aws_credential_types::provider::{provide_credentials_fn, SharedCredentialsProvider};
+    use aws_credential_types::Credentials;
+
+    fn with_provider(provider: impl ProvideCredentials + 'static) -> SdkConfig {
+        SdkConfig::builder()
+            .credentials_provider(SharedCredentialsProvider::new(provider))
+            .build()
+    }
+
+    #[actix_web::test]
+    async fn region_prefers_aws_region_then_the_default_chain() {
+        let chain = Some(Region::new("eu-west-1"));
+        let region = resolve_region(Some("ap-southeast-1".to_string()), chain.clone(), None).await;
+        assert_eq!(region, Some(Region::new("ap-southeast-1")));
+
+        let region = resolve_region(None, chain, Some("us-east-1".to_string())).await;
+        assert_eq!(region, Some(Region::new("eu-west-1")));
+    }
+
+    #[actix_web::test]
+    async fn region_falls_back_to_the_default_region_or_none() {
+        let region = resolve_region(None, None::<Region>, Some("us-east-1".to_string())).await;
+        assert_eq!(region, Some(Region::new("us-east-1")));
+
+        assert_eq!(resolve_region(None, None::<Region>, None).await, None);
+    }
+
+    #[actix_web::test]
+    async fn diagnostics_name_the_credentials_source() {
+        let config = with_provider(Credentials::new("AKIDTEST", "secret", None, None, "stub"));
+        assert_eq!(credentials_source(&config).await, Ok("stub"));
+    }
+
+    #[actix_web::test]
+    async fn diagnostics_report_unresolvable_credentials() {
+        let config = with_provider(provide_credentials_fn(|| async {
+            Err(CredentialsError::not_loaded("no profile found"))
+        }));
+        let message = credentials_source(&config).await.unwrap_err();
+        assert!(message.starts_with("S3 credentials could not be resolved"), "{}", message);
+
+        let message = credentials_source(&SdkConfig::builder().build()).await.unwrap_err();
+        assert_eq!(message, "No S3 credentials provider is configured");
+    }
+}
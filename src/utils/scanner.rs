@@ -0,0 +1,93 @@
+use std::env;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+use log::info;
+
+/// Size of each chunk streamed to clamd
+const CLAMAV_CHUNK_SIZE: usize = 8192;
+
+#[derive(Debug)]
+pub enum ScanResult {
+    Clean,
+    Infected(String),
+    Error(String),
+}
+
+/// Inspects uploaded bytes before they are stored in S3.
+pub trait FileScanner: Send + Sync {
+    fn scan(&self, data: &[u8]) -> ScanResult;
+}
+
+/// Accepts every file; used when no scanner is configured.
+pub struct NoopScanner;
+
+impl FileScanner for NoopScanner {
+    fn scan(&self, _data: &[u8]) -> ScanResult {
+        ScanResult::Clean
+    }
+}
+
+/// Scans files with a clamd daemon using the `INSTREAM` command over TCP.
+pub struct ClamAvScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamAvScanner {
+    pub fn new(address: String) -> Self {
+        ClamAvScanner {
+            address,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    fn instream(&self, data: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        stream.write_all(b"zINSTREAM\0")?;
+        for chunk in data.chunks(CLAMAV_CHUNK_SIZE) {
+            stream.write_all(&(chunk.len() as u32).to_be_bytes())?;
+            stream.write_all(chunk)?;
+        }
+        // A zero-length chunk terminates the stream
+        stream.write_all(&0u32.to_be_bytes())?;
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response.trim_end_matches('\0').trim().to_string())
+    }
+}
+
+impl FileScanner for ClamAvScanner {
+    fn scan(&self, data: &[u8]) -> ScanResult {
+        match self.instream(data) {
+            // Responses look like `stream: OK` or `stream: <signature> FOUND`
+            Ok(response) if response.ends_with("OK") => ScanResult::Clean,
+            Ok(response) if response.ends_with("FOUND") => ScanResult::Infected(
+                response
+                    .trim_start_matches("stream:")
+                    .trim_end_matches("FOUND")
+                    .trim()
+                    .to_string(),
+            ),
+            Ok(response) => ScanResult::Error(response),
+            Err(err) => ScanResult::Error(err.to_string()),
+        }
+    }
+}
+
+/// Builds the scanner selected by `FILE_SCANNER` (`none` or `clamav`).
+pub fn create_file_scanner() -> Arc<dyn FileScanner> {
+    match env::var("FILE_SCANNER").unwrap_or_default().to_lowercase().as_str() {
+        "clamav" => {
+            let address = env::var("CLAMAV_ADDRESS").unwrap_or_else(|_| "127.0.0.1:3310".to_string());
+            info!("Scanning uploads with ClamAV at {}", address);
+            Arc::new(ClamAvScanner::new(address))
+        }
+        _ => Arc::new(NoopScanner),
+    }
+}
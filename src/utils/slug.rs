@@ -0,0 +1,60 @@
+/// Fallback for names without any ASCII letters or digits
+const DEFAULT_SLUG: &str = "department";
+
+/// Lowercases `name` and joins its ASCII alphanumeric runs with dashes,
+/// e.g. "Human Resources" becomes "human-resources".
+pub fn slugify(name: &str) -> String {
+    let slug = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        DEFAULT_SLUG.to_string()
+    } else {
+        slug
+    }
+}
+
+/// Returns `base` if it is not taken, otherwise the first free `base-N` starting at 2.
+pub fn unique_slug(base: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|code| code == base) {
+        return base.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("an unused suffix always exists")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_joins_alphanumeric_runs() {
+        assert_eq!(slugify("Human Resources"), "human-resources");
+        assert_eq!(slugify("  R&D / Ops 2 "), "r-d-ops-2");
+    }
+
+    #[test]
+    fn slugify_falls_back_without_ascii_alphanumerics() {
+        assert_eq!(slugify("---"), DEFAULT_SLUG);
+        assert_eq!(slugify("Équipe"), "quipe");
+        assert_eq!(slugify("営業"), DEFAULT_SLUG);
+    }
+
+    #[test]
+    fn unique_slug_keeps_a_free_base() {
+        assert_eq!(unique_slug("sales", &["marketing".to_string()]), "sales");
+    }
+
+    #[test]
+    fn unique_slug_picks_the_first_free_suffix() {
+        let taken = vec!["sales".to_string(), "sales-2".to_string(), "sales-4".to_string()];
+        assert_eq!(unique_slug("sales", &taken), "sales-3");
+    }
+}
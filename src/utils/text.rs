@@ -0,0 +1,30 @@
+use crate::config;
+
+/// Trims surrounding whitespace from a name before it is validated and stored.
+/// With `NORMALIZE_WHITESPACE`, internal runs of whitespace also collapse to one space.
+pub fn normalize_name(name: &str) -> String {
+    if config::env_flag("NORMALIZE_WHITESPACE") {
+        name.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        name.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::set_env_blocking;
+
+    #[test]
+    fn trims_surrounding_whitespace() {
+        let _env = set_env_blocking(&[("NORMALIZE_WHITESPACE", None)]);
+        assert_eq!(normalize_name("  Human  Resources \t"), "Human  Resources");
+        assert_eq!(normalize_name("   "), "");
+    }
+
+    #[test]
+    fn collapses_inner_whitespace_when_enabled() {
+        let _env = set_env_blocking(&[("NORMALIZE_WHITESPACE", Some("true"))]);
+        assert_eq!(normalize_name("  Human \t  Resources "), "Human Resources");
+    }
+}
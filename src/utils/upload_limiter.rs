@@ -0,0 +1,44 @@
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+/// Number of tracked users above which idle semaphores are dropped
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Caps how many uploads each user may have in flight at once.
+pub struct UploadLimiter {
+    permits_per_user: usize,
+    semaphores: DashMap<Uuid, Arc<Semaphore>>,
+}
+
+impl UploadLimiter {
+    pub fn new(permits_per_user: usize) -> Self {
+        UploadLimiter {
+            permits_per_user,
+            semaphores: DashMap::new(),
+        }
+    }
+
+    /// Returns a permit held for the duration of the upload, or `None` when the user
+    /// already has the maximum number of uploads in progress. Never waits.
+    pub fn try_acquire(&self, user_id: Uuid) -> Option<OwnedSemaphorePermit> {
+        if self.semaphores.len() > PRUNE_THRESHOLD {
+            self.prune_idle();
+        }
+
+        let semaphore = self
+            .semaphores
+            .entry(user_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.permits_per_user)))
+            .clone();
+
+        semaphore.try_acquire_owned().ok()
+    }
+
+    /// Drops semaphores no upload is currently holding a permit from
+    fn prune_idle(&self) {
+        self.semaphores
+            .retain(|_, semaphore| semaphore.available_permits() < self.permits_per_user);
+    }
+}
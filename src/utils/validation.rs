@@ -1,6 +1,157 @@
 use validator::Validate;
+use url::{Host, Url};
+use crate::config;
+use crate::errors::AppError;
+
+/// Schemes accepted for stored image URIs
+const ALLOWED_URL_SCHEMES: &[&str] = &["http", "https"];
 
 pub fn validate_payload<T: Validate>(payload: &T) -> Result<(), actix_web::Error> {
     payload.validate()
         .map_err(|err| actix_web::error::ErrorBadRequest(err))
-}
\ No newline at end of file
+}
+
+/// Validates an image URL: it must be at most `MAX_IMAGE_URI_LEN` characters, use
+/// `http`/`https`, have a host, and that host must be an IP address or a domain
+/// containing at least one dot.
+pub fn validate_image_url(uri: &str) -> Result<(), AppError> {
+    let max_len = config::env_or("MAX_IMAGE_URI_LEN", 2048usize);
+    if uri.chars().count() > max_len {
+        return Err(AppError::BadRequest(format!("URL must be at most {} characters", max_len)));
+    }
+
+    let url = Url::parse(uri)
+        .map_err(|_| AppError::BadRequest("Invalid URL format".to_string()))?;
+
+    // Rejects `javascript:`, `data:` and similar schemes
+    if !ALLOWED_URL_SCHEMES.contains(&url.scheme()) {
+        return Err(AppError::BadRequest("Only http and https URLs are allowed".to_string()));
+    }
+
+    match url.host() {
+        Some(Host::Domain(domain)) if !domain.contains('.') => {
+            Err(AppError::BadRequest("Invalid domain in URL".to_string()))
+        }
+        Some(_) => Ok(()),
+        None => Err(AppError::BadRequest("Missing host in URL".to_string())),
+    }
+}
+
+/// Stricter email check applied on top of `validator`'s when `STRICT_EMAIL` is set: the
+/// local part must not start or end with a dot or contain `..`, and the domain must end
+/// in an alphabetic TLD of at least two letters.
+pub fn validate_strict_email(email: &str) -> Result<(), AppError> {
+    if !config::env_flag("STRICT_EMAIL") {
+        return Ok(());
+    }
+
+    let invalid = || AppError::BadRequest("Invalid email address".to_string());
+    let (local, domain) = email.rsplit_once('@').ok_or_else(invalid)?;
+
+    if local.is_empty() || local.starts_with('.') || local.ends_with('.') || local.contains("..") {
+        return Err(invalid());
+    }
+
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let tld = labels.last().copied().unwrap_or_default();
+    if labels.len() < 2
+        || labels.iter().any(|label| label.is_empty())
+        || tld.len() < 2
+        || !tld.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return Err(invalid());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(uri: &str) -> String {
+        match validate_image_url(uri) {
+            Err(AppError::BadRequest(message)) => message,
+            other => panic!("expected {:?} to be rejected, got {:?}", uri, other),
+        }
+    }
+
+    #[test]
+    fn accepts_http_and_https_domains() {
+        assert!(validate_image_url("https://cdn.example.com/a.png").is_ok());
+        assert!(validate_image_url("http://example.com/a.png?size=2").is_ok());
+    }
+
+    #[test]
+    fn rejects_a_missing_host() {
+        // http(s) URLs can't parse without a host, so these never reach the host check
+        assert_eq!(rejection("http://"), "Invalid URL format");
+        assert_eq!(rejection("https://?q=1"), "Invalid URL format");
+    }
+
+    #[test]
+    fn rejects_domains_without_a_dot() {
+        assert_eq!(rejection("http://localhost/a.png"), "Invalid domain in URL");
+        assert_eq!(rejection("https://intranet/a.png"), "Invalid domain in URL");
+    }
+
+    #[test]
+    fn accepts_ip_hosts() {
+        assert!(validate_image_url("http://127.0.0.1/a.png").is_ok());
+        assert!(validate_image_url("https://[::1]:8443/a.png").is_ok());
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        for uri in ["javascript:alert(1)", "data:image/png;base64,AAAA", "ftp://example.com/a.png"] {
+            assert_eq!(rejection(uri), "Only http and https URLs are allowed");
+        }
+    }
+
+    #[test]
+    fn rejects_unparseable_urls() {
+        assert_eq!(rejection("not a url"), "Invalid URL format");
+    }
+
+    /// An https URL exactly `len` characters long
+    fn url_of_len(len: usize) -> String {
+        let prefix = "https://example.com/";
+        format!("{}{}", prefix, "a".repeat(len - prefix.len()))
+    }
+
+    #[test]
+    fn accepts_urls_up_to_the_default_max_length() {
+        let _env = crate::test_support::set_env_blocking(&[("MAX_IMAGE_URI_LEN", None)]);
+        assert!(validate_image_url(&url_of_len(2048)).is_ok());
+        assert_eq!(rejection(&url_of_len(2049)), "URL must be at most 2048 characters");
+    }
+
+    #[test]
+    fn max_length_is_configurable() {
+        let _env = crate::test_support::set_env_blocking(&[("MAX_IMAGE_URI_LEN", Some("64"))]);
+        assert!(validate_image_url(&url_of_len(64)).is_ok());
+        assert_eq!(rejection(&url_of_len(65)), "URL must be at most 64 characters");
+    }
+
+    #[test]
+    fn strict_email_checks_local_part_and_tld() {
+        let _env = crate::test_support::set_env_blocking(&[("STRICT_EMAIL", Some("1"))]);
+
+        let rejected = ["no-at-sign", "@example.com", ".a@example.com", "a.@example.com",
+            "a..b@example.com", "a@example", "a@example.c", "a@example.c1", "a@.example.com"];
+        let accepted = ["a.b@example.com", "a+tag@mail.example.co"];
+        let results = rejected.iter().chain(&accepted)
+            .map(|email| (*email, validate_strict_email(email).is_ok()))
+            .collect::<Vec<_>>();
+
+        for (email, ok) in results {
+            assert_eq!(ok, accepted.contains(&email), "{}", email);
+        }
+    }
+
+    #[test]
+    fn strict_email_is_off_by_default() {
+        let _env = crate::test_support::set_env_blocking(&[("STRICT_EMAIL", None)]);
+        assert!(validate_strict_email("a..b@x").is_ok());
+    }
+}